@@ -0,0 +1,850 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::Fail;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use toml;
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::density::Density;
+use crate::joinerator::{GeneratorFrequency, GeneratorOptions, Options};
+use crate::limit::{self, LimitUnit};
+use crate::markup::Format;
+use crate::normalize::Normalization;
+use crate::protect::Protector;
+use crate::repertoire::{GlyphPosition, RepertoireSet};
+use crate::transform::{self, Transformer};
+// -------------------------------------------------------------------------------------------------
+
+/// The name of the project-local configuration file.
+pub const LOCAL_FILE: &str = ".joinerator.toml";
+
+/// Settings for creating a `Joinerator` and its transformers.
+/// Every setting is optional, which allows settings from different sources to be merged.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repertoire: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unreadable: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_unit: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit_preset: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub density: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_order: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normalize: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protect: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protect_regex: Option<Vec<String>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transform: Option<Vec<String>>,
+
+    /// Generators in the `POSITION:FREQUENCY:STACKING[:DENSITY]` format (e.g. `above:60%:1`).
+    /// Any number of generators can be given, including several for the same position.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generators: Option<Vec<String>>,
+
+    /// The generator settings, indexed by glyph position name (e.g. `above`).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub generator: BTreeMap<String, GeneratorSettings>,
+}
+
+/// Settings for a single generator.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneratorSettings {
+    /// The frequency (e.g. `60%` or `5`). This defaults to `100%`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<String>,
+
+    /// The maximum number of combining glyphs on a single character. This defaults to `1`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stacking: Option<usize>,
+}
+
+/// A configuration file.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The settings used for every run.
+    pub defaults: Settings,
+
+    /// Named presets, which are applied on top of the defaults.
+    pub preset: BTreeMap<String, Settings>,
+}
+
+/// An error encountered while loading or applying settings.
+#[derive(Debug, Fail)]
+pub enum Error {
+    #[fail(display = "failed to read '{}': {}", path, reason)]
+    ReadError { path: String, reason: String },
+
+    #[fail(display = "failed to write '{}': {}", path, reason)]
+    WriteError { path: String, reason: String },
+
+    #[fail(display = "failed to parse configuration: {}", reason)]
+    ParseError { reason: String },
+
+    #[fail(display = "invalid value for '{}': {}", setting, value)]
+    InvalidSetting { setting: String, value: String },
+
+    #[fail(display = "unknown setting '{}'", name)]
+    UnknownSetting { name: String },
+
+    #[fail(display = "unknown preset '{}'", name)]
+    UnknownPreset { name: String },
+
+    #[fail(display = "unknown repertoire '{}' (available: {})", name, available)]
+    UnknownRepertoire { name: String, available: String },
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl Settings {
+    /// Merges settings on top of these settings.
+    /// Any setting in `other` will replace the existing setting.
+    ///
+    /// Setting a limit preset will also replace the limit and limit unit, unless `other`
+    /// specifies them too. Likewise, setting a list of generators will replace the generators
+    /// indexed by glyph position, and setting a generator by glyph position will replace the list
+    /// of generators.
+    ///
+    /// ## Arguments
+    /// - `other` - The settings to merge.
+    pub fn merge(&mut self, other: &Settings) {
+        if other.limit_preset.is_some() {
+            self.limit = None;
+            self.limit_unit = None;
+        }
+
+        if other.generators.is_some() {
+            self.generator.clear();
+        }
+
+        if !other.generator.is_empty() {
+            self.generators = None;
+        }
+
+        merge_option(&mut self.repertoire, &other.repertoire);
+        merge_option(&mut self.unreadable, &other.unreadable);
+        merge_option(&mut self.limit, &other.limit);
+        merge_option(&mut self.limit_unit, &other.limit_unit);
+        merge_option(&mut self.limit_preset, &other.limit_preset);
+        merge_option(&mut self.seed, &other.seed);
+        merge_option(&mut self.density, &other.density);
+        merge_option(&mut self.format, &other.format);
+        merge_option(&mut self.canonical_order, &other.canonical_order);
+        merge_option(&mut self.normalize, &other.normalize);
+        merge_option(&mut self.protect, &other.protect);
+        merge_option(&mut self.protect_regex, &other.protect_regex);
+        merge_option(&mut self.transform, &other.transform);
+        merge_option(&mut self.generators, &other.generators);
+
+        for (name, generator) in other.generator.iter() {
+            let existing = self.generator.entry(name.clone()).or_default();
+            merge_option(&mut existing.frequency, &generator.frequency);
+            merge_option(&mut existing.stacking, &generator.stacking);
+        }
+    }
+
+    /// Changes a single setting by name (e.g. `limit` or `above.frequency`).
+    ///
+    /// List settings are separated by commas, except for `protect-regex`, which is a single
+    /// pattern. Generator settings are named `POSITION.frequency` and `POSITION.stacking`.
+    /// The new value is merged like any other settings (see `merge`).
+    ///
+    /// ## Arguments
+    /// - `name`  - The setting name, as used in the configuration file.
+    /// - `value` - The new value, or `None` to remove the setting.
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<(), Error> {
+        fn parse<T: std::str::FromStr>(
+            name: &str,
+            value: Option<&str>,
+        ) -> Result<Option<T>, Error> {
+            match value {
+                None => Ok(None),
+                Some(v) => v.parse::<T>().map(Some).map_err(|_| invalid(name, v)),
+            }
+        }
+
+        let string = value.map(|v| v.to_owned());
+        let list = value.map(|v| {
+            v.split(',')
+                .map(|s| s.trim().to_owned())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<String>>()
+        });
+
+        let mut other = Settings::default();
+        match name {
+            "repertoire" => set_option(&mut self.repertoire, &mut other.repertoire, string),
+            "unreadable" => set_option(
+                &mut self.unreadable,
+                &mut other.unreadable,
+                parse(name, value)?,
+            ),
+            "limit" => set_option(&mut self.limit, &mut other.limit, parse(name, value)?),
+            "limit-unit" => set_option(&mut self.limit_unit, &mut other.limit_unit, string),
+            "limit-preset" => set_option(&mut self.limit_preset, &mut other.limit_preset, string),
+            "seed" => set_option(&mut self.seed, &mut other.seed, parse(name, value)?),
+            "density" => set_option(&mut self.density, &mut other.density, string),
+            "format" => set_option(&mut self.format, &mut other.format, string),
+            "canonical-order" => set_option(
+                &mut self.canonical_order,
+                &mut other.canonical_order,
+                parse(name, value)?,
+            ),
+            "normalize" => set_option(&mut self.normalize, &mut other.normalize, string),
+            "protect" => set_option(&mut self.protect, &mut other.protect, list),
+            "protect-regex" => set_option(
+                &mut self.protect_regex,
+                &mut other.protect_regex,
+                string.map(|v| vec![v]),
+            ),
+            "transform" => set_option(&mut self.transform, &mut other.transform, list),
+            "generators" => set_option(&mut self.generators, &mut other.generators, list),
+            _ => {
+                let mut parts = name.splitn(2, ['.', ':']);
+                let position = parts.next().and_then(GlyphPosition::from_name);
+                let (position, field) = match (position, parts.next()) {
+                    (Some(position), Some(field)) => (position, field),
+                    _ => {
+                        return Err(Error::UnknownSetting {
+                            name: name.to_owned(),
+                        })
+                    }
+                };
+
+                let mut generator = GeneratorSettings::default();
+                match field {
+                    "frequency" => generator.frequency = string,
+                    "stacking" => generator.stacking = parse(name, value)?,
+                    _ => {
+                        return Err(Error::UnknownSetting {
+                            name: name.to_owned(),
+                        })
+                    }
+                }
+
+                if value.is_some() {
+                    other
+                        .generator
+                        .insert(position.name().to_owned(), generator);
+                } else if let Some(existing) = self.generator.get_mut(position.name()) {
+                    match field {
+                        "frequency" => existing.frequency = None,
+                        _ => existing.stacking = None,
+                    }
+
+                    if *existing == GeneratorSettings::default() {
+                        self.generator.remove(position.name());
+                    }
+                }
+            }
+        }
+
+        self.merge(&other);
+        Ok(())
+    }
+
+    /// Creates `Joinerator` options from the settings.
+    ///
+    /// ## Arguments
+    /// - `repertoires` - The repertoires that can be used.
+    pub fn options<'a>(&self, repertoires: &'a RepertoireSet) -> Result<Options<'a>, Error> {
+        let name = self
+            .repertoire
+            .as_ref()
+            .map(|s| &s[..])
+            .unwrap_or("default");
+        let repertoire = repertoires
+            .get(name)
+            .ok_or_else(|| Error::UnknownRepertoire {
+                name: name.to_owned(),
+                available: repertoires.names().join(", "),
+            })?;
+
+        let mut options =
+            Options::builder(repertoire).allow_unreadable(self.unreadable == Some(true));
+
+        // Generators.
+        let density = match self.density {
+            None => Density::default(),
+            Some(ref density) => {
+                Density::parse(density).ok_or_else(|| invalid("density", density))?
+            }
+        };
+
+        for (name, generator) in self.generator.iter() {
+            let category =
+                GlyphPosition::from_name(name).ok_or_else(|| invalid("generator", name))?;
+
+            let frequency = generator
+                .frequency
+                .as_ref()
+                .map(|s| &s[..])
+                .unwrap_or("100%");
+            let frequency = GeneratorFrequency::parse(frequency)
+                .ok_or_else(|| invalid(&format!("generator.{}.frequency", name), frequency))?;
+
+            options = options.generator_options(
+                GeneratorOptions::new(category, frequency, generator.stacking.unwrap_or(1))
+                    .density(density.clone()),
+            );
+        }
+
+        for spec in self.generators.iter().flat_map(|v| v.iter()) {
+            options = options.generator_options(
+                GeneratorOptions::parse(spec, &density)
+                    .ok_or_else(|| invalid("generators", spec))?,
+            );
+        }
+
+        // Limit.
+        if let Some(ref name) = self.limit_preset {
+            let preset = limit::preset(name).ok_or_else(|| invalid("limit-preset", name))?;
+            options = options.limit(preset.limit).limit_unit(preset.unit);
+        }
+
+        if let Some(limit) = self.limit {
+            options = options.limit(limit);
+        }
+
+        if let Some(ref unit) = self.limit_unit {
+            options = options
+                .limit_unit(LimitUnit::from_name(unit).ok_or_else(|| invalid("limit-unit", unit))?);
+        }
+
+        if let Some(seed) = self.seed {
+            options = options.seed(seed);
+        }
+
+        // Normalization.
+        options = options.canonical_order(self.canonical_order == Some(true));
+        if let Some(ref name) = self.normalize {
+            options = options.normalize(
+                Normalization::from_name(name).ok_or_else(|| invalid("normalize", name))?,
+            );
+        }
+
+        // Protection.
+        let format = self.format()?;
+        if format != Format::Plain {
+            options = options.protect(Protector::Markup(format));
+        }
+
+        for name in self.protect.iter().flat_map(|v| v.iter()) {
+            match &name[..] {
+                "all" => {
+                    for protector in Protector::all() {
+                        options = options.protect(protector);
+                    }
+                }
+                name => {
+                    options = options.protect(
+                        Protector::from_name(name).ok_or_else(|| invalid("protect", name))?,
+                    );
+                }
+            }
+        }
+
+        for pattern in self.protect_regex.iter().flat_map(|v| v.iter()) {
+            options = options.protect(Protector::Pattern(
+                Regex::new(pattern).map_err(|_| invalid("protect-regex", pattern))?,
+            ));
+        }
+
+        Ok(options.build())
+    }
+
+    /// Creates the transformers from the settings.
+    pub fn transformers(&self) -> Result<Vec<Box<dyn Transformer>>, Error> {
+        self.transform
+            .iter()
+            .flat_map(|v| v.iter())
+            .map(|name| transform::get(name, self.seed).ok_or_else(|| invalid("transform", name)))
+            .collect()
+    }
+
+    /// Gets the input format from the settings.
+    pub fn format(&self) -> Result<Format, Error> {
+        match self.format {
+            None => Ok(Format::default()),
+            Some(ref format) => Format::from_name(format).ok_or_else(|| invalid("format", format)),
+        }
+    }
+
+    /// Serializes the settings as TOML.
+    pub fn to_toml(&self) -> Result<String, Error> {
+        toml::to_string(self).map_err(|e| Error::ParseError {
+            reason: e.to_string(),
+        })
+    }
+}
+
+impl Config {
+    /// Creates a configuration by deserializing TOML data.
+    pub fn from_toml(data: &str) -> Result<Self, Error> {
+        toml::from_str(data).map_err(|e| Error::ParseError {
+            reason: e.to_string(),
+        })
+    }
+
+    /// Creates a configuration by reading a TOML file.
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path).map_err(|e| Error::ReadError {
+            path: path.to_string_lossy().into_owned(),
+            reason: e.to_string(),
+        })?;
+
+        Self::from_toml(&data).map_err(|e| Error::ReadError {
+            path: path.to_string_lossy().into_owned(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// Loads and merges every configuration file that exists (see `paths`).
+    pub fn load() -> Result<Self, Error> {
+        let mut config = Config::default();
+        for path in paths() {
+            if path.is_file() {
+                config.merge(Config::from_file(&path)?);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Merges another configuration on top of this one.
+    /// Presets with the same name are merged, with `other` taking priority.
+    pub fn merge(&mut self, other: Config) {
+        self.defaults.merge(&other.defaults);
+        for (name, preset) in other.preset.into_iter() {
+            self.preset.entry(name).or_default().merge(&preset);
+        }
+    }
+
+    /// Gets the settings of a preset, merged on top of the defaults.
+    ///
+    /// ## Arguments
+    /// - `name` - The preset name, or `None` for only the defaults.
+    pub fn settings(&self, name: Option<&str>) -> Result<Settings, Error> {
+        let mut settings = self.defaults.clone();
+        if let Some(name) = name {
+            let preset = self.preset.get(name).ok_or_else(|| Error::UnknownPreset {
+                name: name.to_owned(),
+            })?;
+
+            settings.merge(preset);
+        }
+
+        Ok(settings)
+    }
+}
+
+/// Gets the paths of the configuration files, from lowest to highest priority.
+///
+/// This is `$XDG_CONFIG_HOME/joinerator/config.toml` (or `~/.config/joinerator/config.toml`),
+/// followed by the closest `.joinerator.toml` in the current directory or its parents.
+pub fn paths() -> Vec<PathBuf> {
+    let mut paths = vec![];
+    if let Some(user_path) = user_path() {
+        paths.push(user_path);
+    }
+
+    if let Ok(cwd) = env::current_dir() {
+        if let Some(dir) = cwd.ancestors().find(|dir| dir.join(LOCAL_FILE).is_file()) {
+            paths.push(dir.join(LOCAL_FILE));
+        }
+    }
+
+    paths
+}
+
+/// Gets the path of the user's configuration file.
+/// This is `$XDG_CONFIG_HOME/joinerator/config.toml` (or `~/.config/joinerator/config.toml`).
+pub fn user_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|config_home| config_home.join("joinerator").join("config.toml"))
+}
+
+/// Saves settings as a preset in a configuration file, creating the file if needed.
+/// Only the `[preset.NAME]` table is replaced, so the rest of the file (including its comments and
+/// formatting) is left unchanged.
+///
+/// ## Arguments
+/// - `path`     - The path of the configuration file.
+/// - `name`     - The preset name.
+/// - `settings` - The settings of the preset.
+pub fn save_preset<P>(path: P, name: &str, settings: &Settings) -> Result<(), Error>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let write_error = |reason: String| Error::WriteError {
+        path: path.to_string_lossy().into_owned(),
+        reason,
+    };
+
+    let mut document = if path.is_file() {
+        let data = fs::read_to_string(path).map_err(|e| write_error(e.to_string()))?;
+        Config::from_toml(&data).map_err(|e| write_error(e.to_string()))?;
+        data.parse::<DocumentMut>()
+            .map_err(|e| write_error(e.to_string()))?
+    } else {
+        DocumentMut::new()
+    };
+
+    let mut preset = settings
+        .to_toml()?
+        .parse::<DocumentMut>()
+        .map_err(|e| write_error(e.to_string()))?
+        .as_table()
+        .clone();
+
+    // Tables are written in the order of their positions, so a replaced preset keeps its place and
+    // a new preset is added to the end of the file.
+    let presets = document
+        .entry("preset")
+        .or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        })
+        .as_table_mut()
+        .ok_or_else(|| write_error("'preset' is not a table".to_owned()))?;
+
+    let position = match presets.get(name).and_then(Item::as_table) {
+        Some(existing) => existing.position(),
+        None => None,
+    };
+
+    let position = position.unwrap_or_else(|| last_position(document.as_table()) + 1);
+    set_position(&mut preset, position);
+    preset.set_implicit(false);
+    document["preset"][name] = Item::Table(preset);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| write_error(e.to_string()))?;
+    }
+
+    fs::write(path, document.to_string()).map_err(|e| write_error(e.to_string()))
+}
+
+// -------------------------------------------------------------------------------------------------
+
+fn last_position(table: &Table) -> usize {
+    table
+        .iter()
+        .filter_map(|(_, item)| item.as_table())
+        .map(last_position)
+        .chain(table.position())
+        .max()
+        .unwrap_or(0)
+}
+
+fn set_position(table: &mut Table, position: usize) {
+    table.set_position(position);
+    for (_, item) in table.iter_mut() {
+        if let Some(table) = item.as_table_mut() {
+            set_position(table, position);
+        }
+    }
+}
+
+fn merge_option<T: Clone>(target: &mut Option<T>, source: &Option<T>) {
+    if source.is_some() {
+        *target = source.clone();
+    }
+}
+
+/// Prepares a single setting for `Settings::set`.
+/// A new value is put into the settings that will be merged, and a removed value is cleared
+/// immediately.
+fn set_option<T>(target: &mut Option<T>, other: &mut Option<T>, value: Option<T>) {
+    match value {
+        Some(_) => *other = value,
+        None => *target = None,
+    }
+}
+
+fn invalid(setting: &str, value: &str) -> Error {
+    Error::InvalidSetting {
+        setting: setting.to_owned(),
+        value: value.to_owned(),
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[defaults]
+limit-preset = "discord"
+transform = ["uppercase"]
+
+[defaults.generator.above]
+frequency = "50%"
+stacking = 2
+
+[preset.spooky]
+repertoire = "symbols"
+limit = 100
+
+[preset.spooky.generator.above]
+stacking = 4
+
+[preset.listed]
+generators = ["below:100%:1"]
+"#;
+
+    #[test]
+    fn from_toml() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        assert_eq!(config.defaults.limit_preset, Some("discord".to_owned()));
+        assert_eq!(config.preset.len(), 2);
+        assert!(Config::from_toml("[defaults]\nunknown = 1").is_err());
+    }
+
+    #[test]
+    fn settings_merge_presets_on_defaults() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        let settings = config.settings(Some("spooky")).unwrap();
+
+        assert_eq!(settings.repertoire, Some("symbols".to_owned()));
+        assert_eq!(settings.transform, Some(vec!["uppercase".to_owned()]));
+        assert_eq!(settings.limit, Some(100));
+        assert_eq!(
+            settings.generator["above"].frequency,
+            Some("50%".to_owned())
+        );
+        assert_eq!(settings.generator["above"].stacking, Some(4));
+
+        assert!(config.settings(Some("missing")).is_err());
+        assert_eq!(config.settings(None).unwrap(), config.defaults);
+    }
+
+    #[test]
+    fn merge_limit_preset_replaces_limit() {
+        let mut settings = Settings {
+            limit: Some(10),
+            limit_unit: Some("bytes".to_owned()),
+            ..Settings::default()
+        };
+
+        settings.merge(&Settings {
+            limit_preset: Some("irc".to_owned()),
+            ..Settings::default()
+        });
+
+        assert_eq!(settings.limit, None);
+        assert_eq!(settings.limit_unit, None);
+        assert_eq!(settings.limit_preset, Some("irc".to_owned()));
+    }
+
+    #[test]
+    fn merge_generators_replace_generator_map() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        let settings = config.settings(Some("listed")).unwrap();
+
+        assert!(settings.generator.is_empty());
+        assert_eq!(settings.generators, Some(vec!["below:100%:1".to_owned()]));
+    }
+
+    #[test]
+    fn merge_generator_map_replaces_generators() {
+        let config = Config::from_toml(CONFIG).unwrap();
+        let mut settings = config.settings(Some("listed")).unwrap();
+        let mut generator = BTreeMap::new();
+        generator.insert(
+            "through".to_owned(),
+            GeneratorSettings {
+                frequency: None,
+                stacking: Some(2),
+            },
+        );
+
+        settings.merge(&Settings {
+            generator,
+            ..Settings::default()
+        });
+
+        assert_eq!(settings.generators, None);
+        assert_eq!(settings.generator.len(), 1);
+        assert_eq!(settings.generator["through"].stacking, Some(2));
+    }
+
+    #[test]
+    fn config_merge_merges_presets() {
+        let mut config = Config::from_toml(CONFIG).unwrap();
+        config.merge(Config::from_toml("[preset.spooky]\nseed = 3").unwrap());
+
+        let preset = &config.preset["spooky"];
+        assert_eq!(preset.seed, Some(3));
+        assert_eq!(preset.limit, Some(100));
+    }
+
+    #[test]
+    fn options() {
+        let repertoires = RepertoireSet::with_builtin();
+        let config = Config::from_toml(CONFIG).unwrap();
+        let options = config
+            .settings(Some("spooky"))
+            .unwrap()
+            .options(&repertoires)
+            .unwrap();
+
+        assert_eq!(options.repertoire.name, "symbols");
+        assert_eq!(options.limit, Some(100));
+        assert_eq!(options.limit_unit, LimitUnit::Utf16Units);
+        assert_eq!(options.generator.len(), 1);
+        assert_eq!(options.generator[0].stacking, 4);
+    }
+
+    #[test]
+    fn options_reject_invalid_settings() {
+        let repertoires = RepertoireSet::with_builtin();
+        let invalid = [
+            "repertoire = \"missing\"",
+            "density = \"sideways\"",
+            "limit-preset = \"myspace\"",
+            "protect = [\"emails\"]",
+            "protect-regex = [\"(\"]",
+            "generators = [\"above:0%:1\"]",
+        ];
+
+        for setting in invalid.iter() {
+            let settings: Settings = toml::from_str(setting).unwrap();
+            assert!(settings.options(&repertoires).is_err(), "{}", setting);
+        }
+    }
+
+    #[test]
+    fn set_follows_merge_rules() {
+        let mut settings = Config::from_toml(CONFIG).unwrap().defaults;
+        settings.set("limit", Some("10")).unwrap();
+        settings.set("limit-unit", Some("bytes")).unwrap();
+        settings.set("limit-preset", Some("twitter")).unwrap();
+        assert_eq!(settings.limit, None);
+        assert_eq!(settings.limit_unit, None);
+        assert_eq!(settings.limit_preset, Some("twitter".to_owned()));
+
+        settings
+            .set("generators", Some("above:1:1, below:2:2"))
+            .unwrap();
+        assert!(settings.generator.is_empty());
+        assert_eq!(
+            settings.generators,
+            Some(vec!["above:1:1".to_owned(), "below:2:2".to_owned()])
+        );
+
+        settings.set("above.stacking", Some("3")).unwrap();
+        assert_eq!(settings.generators, None);
+        assert_eq!(settings.generator["above"].stacking, Some(3));
+    }
+
+    #[test]
+    fn set_removes_settings() {
+        let mut settings = Config::from_toml(CONFIG).unwrap().defaults;
+        settings.set("transform", None).unwrap();
+        settings.set("above.frequency", None).unwrap();
+        assert_eq!(settings.transform, None);
+        assert_eq!(settings.generator["above"].stacking, Some(2));
+
+        settings.set("above:stacking", None).unwrap();
+        settings.set("limit-preset", None).unwrap();
+        assert!(settings.generator.is_empty());
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn set_rejects_invalid_settings() {
+        let mut settings = Settings::default();
+        assert!(settings.set("limit", Some("many")).is_err());
+        assert!(settings.set("above.stacking", Some("-1")).is_err());
+        assert!(settings.set("above.color", Some("red")).is_err());
+        assert!(settings.set("sideways.stacking", Some("1")).is_err());
+        assert!(settings.set("unknown", Some("1")).is_err());
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn save_preset_only_changes_the_preset() {
+        let dir = env::temp_dir().join(format!("joinerator-save-preset-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let _ = fs::remove_dir_all(&dir);
+
+        let settings = Settings {
+            seed: Some(1),
+            generators: Some(vec!["above:100%:2".to_owned()]),
+            ..Settings::default()
+        };
+
+        save_preset(&path, "new", &settings).unwrap();
+        assert_eq!(Config::from_file(&path).unwrap().preset["new"], settings);
+
+        let original = format!("# My settings.\n{}", CONFIG);
+        fs::write(&path, &original).unwrap();
+        save_preset(&path, "spooky", &settings).unwrap();
+        save_preset(&path, "new", &settings).unwrap();
+
+        let data = fs::read_to_string(&path).unwrap();
+        let config = Config::from_toml(&data).unwrap();
+        assert!(data.starts_with("# My settings.\n\n[defaults]\nlimit-preset = \"discord\""));
+        assert!(data.find("[preset.spooky]") < data.find("[preset.listed]"));
+        assert!(data.find("[preset.listed]") < data.find("[preset.new]"));
+        assert_eq!(config.defaults, Config::from_toml(CONFIG).unwrap().defaults);
+        assert_eq!(config.preset["spooky"], settings);
+        assert_eq!(config.preset["new"], settings);
+        assert_eq!(config.preset.len(), 3);
+
+        fs::write(&path, "[defaults]\nunknown = 1").unwrap();
+        assert!(save_preset(&path, "new", &settings).is_err());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[defaults]\nunknown = 1"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
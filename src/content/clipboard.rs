@@ -6,7 +6,7 @@ use failure::{err_msg, Error};
 
 use std::sync::Mutex;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::content::{Consumer, Provider};
 // -------------------------------------------------------------------------------------------------
@@ -91,11 +91,41 @@ impl ClipboardSingleton {
 
 // -------------------------------------------------------------------------------------------------
 
-pub struct ClipboardProvider {}
+/// The default shortest time between checks for clipboard changes.
+pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The default longest time between checks for clipboard changes.
+pub const DEFAULT_MAX_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// A data provider that reads from the clipboard.
+///
+/// The clipboard can't notify about changes, so it has to be checked. The time between checks
+/// starts at the minimum interval, and doubles up to the maximum interval while nothing changes.
+pub struct ClipboardProvider {
+    min_interval: Duration,
+    max_interval: Duration,
+    interval: Duration,
+}
 
 impl ClipboardProvider {
     pub fn new() -> Self {
-        ClipboardProvider {}
+        ClipboardProvider {
+            min_interval: DEFAULT_MIN_INTERVAL,
+            max_interval: DEFAULT_MAX_INTERVAL,
+            interval: DEFAULT_MIN_INTERVAL,
+        }
+    }
+
+    /// Sets the shortest and longest time between checks for clipboard changes.
+    ///
+    /// ## Arguments
+    /// - `min` - The shortest time, used right after a change.
+    /// - `max` - The longest time, used after the clipboard hasn't changed for a while.
+    pub fn with_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_interval = min;
+        self.max_interval = max.max(min);
+        self.interval = min;
+        self
     }
 }
 
@@ -111,6 +141,24 @@ impl Provider for ClipboardProvider {
         let clipboard = lock.as_mut().unwrap();
         clipboard.has_changed()
     }
+
+    fn wait_for_change(&mut self, timeout: Duration) -> Result<bool, Error> {
+        let start = Instant::now();
+        loop {
+            if self.has_more()? {
+                self.interval = self.min_interval;
+                return Ok(true);
+            }
+
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Ok(false);
+            }
+
+            sleep(self.interval.min(timeout - elapsed));
+            self.interval = (self.interval * 2).min(self.max_interval);
+        }
+    }
 }
 
 pub struct ClipboardConsumer {}
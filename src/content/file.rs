@@ -0,0 +1,447 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use failure::{err_msg, Error};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+#[cfg(target_os = "linux")]
+use inotify::{Inotify, WatchMask};
+#[cfg(target_os = "linux")]
+use std::ffi::OsString;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+#[cfg(target_os = "linux")]
+use std::time::Instant;
+
+use crate::content::{self, Consumer, Provider};
+// -------------------------------------------------------------------------------------------------
+
+lazy_static! {
+    /// The files written by every `FileConsumer`, and their status after being written.
+    /// This is used to keep a `FileProvider` from providing the output of its own process.
+    static ref WRITTEN: Mutex<HashMap<PathBuf, FileStat>> = Mutex::new(HashMap::new());
+}
+
+type FileStat = (Option<SystemTime>, u64);
+
+/// A data provider that reads the entire contents of a file.
+/// The file will be provided again whenever it is modified by something else.
+///
+/// On Linux, changes are detected with inotify. Otherwise, the file is checked periodically.
+pub struct FileProvider {
+    path: PathBuf,
+    read: Option<FileStat>,
+
+    #[cfg(target_os = "linux")]
+    watcher: Option<Watcher>,
+}
+
+/// An inotify watch on the directory containing a file.
+/// The directory is watched so that files which are replaced by renaming are still detected.
+#[cfg(target_os = "linux")]
+struct Watcher {
+    inotify: Inotify,
+    name: OsString,
+    buffer: Vec<u8>,
+}
+
+/// How a `FileConsumer` writes to its file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum WriteMode {
+    /// The file is truncated before the first string is written.
+    Overwrite,
+
+    /// Every string is appended to the end of the file.
+    Append,
+
+    /// Every string replaces the file atomically, by writing to a temporary file and renaming it.
+    Atomic,
+}
+
+/// A data consumer that writes to a file.
+pub struct FileConsumer {
+    path: PathBuf,
+    mode: WriteMode,
+    replace: bool,
+    file: Option<File>,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl FileProvider {
+    /// Creates a file provider.
+    ///
+    /// ## Arguments
+    /// - `path` - The path of the file to read.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        FileProvider {
+            path: path.as_ref().to_owned(),
+            read: None,
+
+            #[cfg(target_os = "linux")]
+            watcher: None,
+        }
+    }
+
+    fn stat(&self) -> Result<FileStat, Error> {
+        stat(&self.path).map_err(|e| io_error(&self.path, e))
+    }
+}
+
+impl Provider for FileProvider {
+    fn provide(&mut self) -> Result<String, Error> {
+        let stat = self.stat()?;
+        let data = fs::read_to_string(&self.path).map_err(|e| io_error(&self.path, e))?;
+        self.read = Some(stat);
+        Ok(data)
+    }
+
+    fn has_more(&mut self) -> Result<bool, Error> {
+        match self.read {
+            None => Ok(true),
+            Some(_) => {
+                let current = self.stat()?;
+                let written = fs::canonicalize(&self.path)
+                    .ok()
+                    .and_then(|path| WRITTEN.lock().unwrap().get(&path).cloned());
+
+                if written == Some(current) {
+                    self.read = Some(current);
+                }
+
+                Ok(self.read != Some(current))
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn wait_for_change(&mut self, timeout: Duration) -> Result<bool, Error> {
+        if self.has_more()? {
+            return Ok(true);
+        }
+
+        if self.watcher.is_none() {
+            match Watcher::new(&self.path) {
+                Ok(watcher) => self.watcher = Some(watcher),
+                Err(_) => return content::poll_for_change(self, timeout, content::POLL_INTERVAL),
+            }
+        }
+
+        let start = Instant::now();
+        loop {
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Ok(false);
+            }
+
+            let watcher = self.watcher.as_mut().unwrap();
+            let changed = watcher
+                .wait(timeout - elapsed)
+                .map_err(|e| io_error(&self.path, e))?;
+
+            if changed && self.has_more()? {
+                return Ok(true);
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn wait_for_change(&mut self, timeout: Duration) -> Result<bool, Error> {
+        content::poll_for_change(self, timeout, content::POLL_INTERVAL)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Watcher {
+    /// Starts watching for changes to a file.
+    ///
+    /// ## Arguments
+    /// - `path` - The path of the file.
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file"))?;
+
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+
+        let mut inotify = Inotify::init()?;
+        inotify.add_watch(
+            directory,
+            WatchMask::MODIFY | WatchMask::CLOSE_WRITE | WatchMask::CREATE | WatchMask::MOVED_TO,
+        )?;
+
+        Ok(Watcher {
+            inotify,
+            name: name.to_owned(),
+            buffer: vec![0; 4096],
+        })
+    }
+
+    /// Waits for the file to change, or for the timeout to elapse.
+    ///
+    /// ## Returns
+    /// True if the file was changed.
+    pub fn wait(&mut self, timeout: Duration) -> io::Result<bool> {
+        let mut descriptor = libc::pollfd {
+            fd: self.inotify.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        let millis = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        let ready = unsafe { libc::poll(&mut descriptor, 1, millis) };
+        if ready < 0 {
+            let error = io::Error::last_os_error();
+            return match error.kind() {
+                io::ErrorKind::Interrupted => Ok(false),
+                _ => Err(error),
+            };
+        }
+
+        if ready == 0 {
+            return Ok(false);
+        }
+
+        let name = &self.name;
+        let events = self.inotify.read_events(&mut self.buffer)?;
+        let changed = events
+            .into_iter()
+            .any(|event| event.name.map(|n| n == name.as_os_str()).unwrap_or(false));
+        Ok(changed)
+    }
+}
+
+impl FileConsumer {
+    /// Creates a file consumer.
+    ///
+    /// ## Arguments
+    /// - `path` - The path of the file to write.
+    /// - `mode` - How the file is written to.
+    pub fn new<P: AsRef<Path>>(path: P, mode: WriteMode) -> Self {
+        FileConsumer {
+            path: path.as_ref().to_owned(),
+            mode,
+            replace: false,
+            file: None,
+        }
+    }
+
+    /// Makes every string replace the file when overwriting, instead of following the strings
+    /// written before it. The file is opened again every time, so it is still written to after
+    /// being replaced by something else (e.g. an editor that saves by renaming).
+    ///
+    /// This is used when watching for changes, where every string is a new version of the output.
+    pub fn with_replace(mut self, replace: bool) -> Self {
+        self.replace = replace;
+        self
+    }
+
+    fn open(&mut self) -> Result<&mut File, Error> {
+        if self.replace && self.mode == WriteMode::Overwrite {
+            self.file = None;
+        }
+
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(self.mode == WriteMode::Append)
+                .truncate(self.mode == WriteMode::Overwrite)
+                .open(&self.path)
+                .map_err(|e| io_error(&self.path, e))?;
+
+            self.file = Some(file);
+        }
+
+        Ok(self.file.as_mut().unwrap())
+    }
+
+    /// Remembers the status of the file after it was written.
+    fn remember(&self) {
+        if let (Ok(path), Ok(stat)) = (fs::canonicalize(&self.path), stat(&self.path)) {
+            WRITTEN.lock().unwrap().insert(path, stat);
+        }
+    }
+
+    /// Replaces the file by writing to a temporary file in the same directory and renaming it.
+    /// The permissions of the existing file are kept.
+    fn replace(&self, str: &str) -> Result<(), Error> {
+        let name = self
+            .path
+            .file_name()
+            .ok_or_else(|| err_msg(format!("'{}' is not a file", self.path.display())))?;
+
+        let temp =
+            self.path
+                .with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), process::id()));
+
+        let result = (|| -> io::Result<()> {
+            let mut file = File::create(&temp)?;
+            file.write_all(str.as_bytes())?;
+            file.sync_all()?;
+
+            if let Ok(metadata) = fs::metadata(&self.path) {
+                fs::set_permissions(&temp, metadata.permissions())?;
+            }
+
+            fs::rename(&temp, &self.path)
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+
+        result.map_err(|e| io_error(&self.path, e))
+    }
+}
+
+impl Consumer for FileConsumer {
+    fn consume(&mut self, str: String) -> Result<(), Error> {
+        if self.mode == WriteMode::Atomic {
+            self.replace(&str)?;
+        } else {
+            let path = self.path.clone();
+            let file = self.open()?;
+            file.write_all(str.as_bytes())
+                .and_then(|_| file.flush())
+                .map_err(|e| io_error(&path, e))?;
+        }
+
+        self.remember();
+        Ok(())
+    }
+}
+
+fn stat(path: &Path) -> io::Result<FileStat> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.modified().ok(), metadata.len()))
+}
+
+fn io_error(path: &Path, error: io::Error) -> Error {
+    err_msg(format!("'{}': {}", path.display(), error))
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// Creates an empty temporary directory.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("joinerator-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn consume_all(consumer: &mut FileConsumer, strings: &[&str]) {
+        for str in strings {
+            consumer.consume(str.to_string()).unwrap();
+        }
+    }
+
+    #[test]
+    fn overwrite_truncates_once() {
+        let path = temp_dir("file-overwrite").join("out.txt");
+        fs::write(&path, "old contents").unwrap();
+
+        consume_all(
+            &mut FileConsumer::new(&path, WriteMode::Overwrite),
+            &["a\n", "b\n"],
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb\n");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn overwrite_with_replace_truncates_every_time() {
+        let path = temp_dir("file-replace").join("out.txt");
+        let mut consumer = FileConsumer::new(&path, WriteMode::Overwrite).with_replace(true);
+
+        consume_all(&mut consumer, &["first version\n", "second\n"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+
+        // Saving by renaming replaces the file the consumer opened.
+        let saved = path.with_file_name("saved.txt");
+        fs::write(&saved, "edited").unwrap();
+        fs::rename(&saved, &path).unwrap();
+
+        consume_all(&mut consumer, &["third\n"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third\n");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn append() {
+        let path = temp_dir("file-append").join("out.txt");
+        fs::write(&path, "old\n").unwrap();
+
+        let mut consumer = FileConsumer::new(&path, WriteMode::Append).with_replace(true);
+        consume_all(&mut consumer, &["a\n", "b\n"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old\na\nb\n");
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn atomic() {
+        let dir = temp_dir("file-atomic");
+        let path = dir.join("out.txt");
+
+        consume_all(
+            &mut FileConsumer::new(&path, WriteMode::Atomic),
+            &["a\n", "b\n"],
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "b\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn provider_ignores_its_own_output() {
+        let path = temp_dir("file-provider").join("notes.txt");
+        fs::write(&path, "hello").unwrap();
+
+        let mut provider = FileProvider::new(&path);
+        assert!(provider.has_more().unwrap());
+        assert_eq!(provider.provide().unwrap(), "hello");
+
+        consume_all(
+            &mut FileConsumer::new(&path, WriteMode::Atomic),
+            &["h\u{0301}ello"],
+        );
+        assert!(!provider.has_more().unwrap());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn provider_waits_for_changes() {
+        let path = temp_dir("file-wait").join("notes.txt");
+        fs::write(&path, "hello").unwrap();
+
+        let mut provider = FileProvider::new(&path);
+        provider.provide().unwrap();
+        assert!(!provider.wait_for_change(Duration::from_millis(50)).unwrap());
+
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            fs::write(&writer_path, "hello, world").unwrap();
+        });
+
+        assert!(provider.wait_for_change(Duration::from_secs(5)).unwrap());
+        assert_eq!(provider.provide().unwrap(), "hello, world");
+        writer.join().unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -1,22 +1,73 @@
 // -------------------------------------------------------------------------------------------------
 // joinerator | Copyright (C) 2019 eth-p
 // -------------------------------------------------------------------------------------------------
+pub mod file;
 pub mod null;
 pub mod streams;
 pub mod strings;
+pub mod tee;
 
 #[cfg(feature = "clipboard_support")]
 pub mod clipboard;
 
 // -------------------------------------------------------------------------------------------------
 use failure::Error;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
 // -------------------------------------------------------------------------------------------------
 
+/// How often `Provider::wait_for_change` checks `has_more` by default.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// A content provider.
 /// This provides one or more strings to be processed by Joinerator.
 pub trait Provider {
     fn provide(&mut self) -> Result<String, Error>;
     fn has_more(&mut self) -> Result<bool, Error>;
+
+    /// Waits until the provider has more content, or until the timeout elapses.
+    /// By default, this checks `has_more` every `POLL_INTERVAL`.
+    ///
+    /// ## Arguments
+    /// - `timeout` - The maximum time to wait.
+    ///
+    /// ## Returns
+    /// True if the provider has more content.
+    fn wait_for_change(&mut self, timeout: Duration) -> Result<bool, Error> {
+        poll_for_change(self, timeout, POLL_INTERVAL)
+    }
+}
+
+/// Waits until a provider has more content by checking `has_more` at a fixed interval.
+///
+/// ## Arguments
+/// - `provider` - The provider.
+/// - `timeout`  - The maximum time to wait.
+/// - `interval` - The time between checks.
+///
+/// ## Returns
+/// True if the provider has more content.
+pub fn poll_for_change<P>(
+    provider: &mut P,
+    timeout: Duration,
+    interval: Duration,
+) -> Result<bool, Error>
+where
+    P: Provider + ?Sized,
+{
+    let start = Instant::now();
+    loop {
+        if provider.has_more()? {
+            return Ok(true);
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Ok(false);
+        }
+
+        sleep(interval.min(timeout - elapsed));
+    }
 }
 
 /// A content consumer.
@@ -24,3 +75,47 @@ pub trait Provider {
 pub trait Consumer {
     fn consume(&mut self, str: String) -> Result<(), Error>;
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A provider that has more content after a number of checks.
+    struct Countdown(usize);
+
+    impl Provider for Countdown {
+        fn provide(&mut self) -> Result<String, Error> {
+            Ok(String::new())
+        }
+
+        fn has_more(&mut self) -> Result<bool, Error> {
+            match self.0 {
+                0 => Ok(true),
+                _ => {
+                    self.0 -= 1;
+                    Ok(false)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn poll_for_change_waits_for_content() {
+        let mut provider = Countdown(3);
+        let interval = Duration::from_millis(1);
+        assert!(poll_for_change(&mut provider, Duration::from_secs(5), interval).unwrap());
+        assert_eq!(provider.0, 0);
+    }
+
+    #[test]
+    fn poll_for_change_times_out() {
+        let mut provider = Countdown(usize::MAX);
+        let start = Instant::now();
+        let timeout = Duration::from_millis(30);
+        assert!(!provider.wait_for_change(timeout).unwrap());
+        assert!(start.elapsed() >= timeout);
+        assert!(start.elapsed() < timeout + POLL_INTERVAL * 5);
+    }
+}
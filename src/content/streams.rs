@@ -3,7 +3,8 @@
 // -------------------------------------------------------------------------------------------------
 use failure::Error;
 use std::io;
-use std::io::{Read, Stdin, Stdout, Write};
+use std::io::{BufRead, BufReader, Read, Stdin, Stdout, Write};
+use std::str;
 
 use crate::content::{Consumer, Provider};
 // -------------------------------------------------------------------------------------------------
@@ -55,6 +56,181 @@ impl Provider for StdinProvider {
 
 // -------------------------------------------------------------------------------------------------
 
+/// The delimiter between the records of a `StreamProvider`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RecordDelimiter {
+    /// Every line is a record.
+    Line,
+
+    /// Every paragraph (lines followed by a blank line) is a record.
+    Paragraph,
+
+    /// Records are separated by NUL characters.
+    Nul,
+}
+
+/// The names of every record delimiter that can be created with `RecordDelimiter::from_name`.
+pub const DELIMITER_NAMES: &[&str] = &["line", "paragraph", "nul"];
+
+/// The default maximum length of a record, in bytes.
+pub const DEFAULT_MAX_RECORD: usize = 64 * 1024;
+
+/// A data provider that reads records from a stream as they arrive.
+///
+/// Every record includes its delimiter, so the records can be joined to get the original stream.
+/// Records longer than the maximum length are split, which keeps memory use bounded.
+pub struct StreamProvider<R: Read> {
+    reader: BufReader<R>,
+    delimiter: RecordDelimiter,
+    max_length: usize,
+    buffer: Vec<u8>,
+    next: Option<String>,
+}
+
+impl RecordDelimiter {
+    /// Creates a record delimiter from its name (see `DELIMITER_NAMES`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "line" => Some(RecordDelimiter::Line),
+            "paragraph" => Some(RecordDelimiter::Paragraph),
+            "nul" | "null" => Some(RecordDelimiter::Nul),
+            _ => None,
+        }
+    }
+
+    fn byte(self) -> u8 {
+        match self {
+            RecordDelimiter::Line | RecordDelimiter::Paragraph => b'\n',
+            RecordDelimiter::Nul => b'\0',
+        }
+    }
+}
+
+impl StreamProvider<Stdin> {
+    /// Creates a stream provider that reads from standard input.
+    ///
+    /// ## Arguments
+    /// - `delimiter` - The delimiter between records.
+    pub fn stdin(delimiter: RecordDelimiter) -> Self {
+        Self::new(io::stdin(), delimiter)
+    }
+}
+
+impl<R: Read> StreamProvider<R> {
+    /// Creates a stream provider.
+    ///
+    /// ## Arguments
+    /// - `stream`    - The stream to read from.
+    /// - `delimiter` - The delimiter between records.
+    pub fn new(stream: R, delimiter: RecordDelimiter) -> Self {
+        StreamProvider {
+            reader: BufReader::new(stream),
+            delimiter,
+            max_length: DEFAULT_MAX_RECORD,
+            buffer: Vec::new(),
+            next: None,
+        }
+    }
+
+    /// Sets the maximum length of a record, in bytes.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length.max(4);
+        self
+    }
+
+    /// Reads the next record.
+    ///
+    /// ## Returns
+    /// The record, or `None` if the end of the stream was reached.
+    fn read_record(&mut self) -> Result<Option<String>, Error> {
+        let delimiter = self.delimiter.byte();
+        let mut line_start = 0;
+
+        loop {
+            let (complete, taken) = {
+                let available = self.reader.fill_buf()?;
+                if available.is_empty() {
+                    break;
+                }
+
+                let space = self.max_length.saturating_sub(self.buffer.len());
+                match available.iter().take(space).position(|b| *b == delimiter) {
+                    Some(index) => {
+                        self.buffer.extend_from_slice(&available[..=index]);
+                        (true, index + 1)
+                    }
+                    None => {
+                        let taken = available.len().min(space);
+                        self.buffer.extend_from_slice(&available[..taken]);
+                        (false, taken)
+                    }
+                }
+            };
+
+            self.reader.consume(taken);
+
+            if complete && self.delimiter == RecordDelimiter::Paragraph {
+                // Paragraphs end at the first blank line after some text.
+                let blank = is_blank(&self.buffer[line_start..]);
+                let has_text = !is_blank(&self.buffer[..line_start]);
+                line_start = self.buffer.len();
+                if !(blank && has_text) && self.buffer.len() < self.max_length {
+                    continue;
+                }
+            }
+
+            if complete || self.buffer.len() >= self.max_length {
+                return self.take_record().map(Some);
+            }
+        }
+
+        if self.buffer.is_empty() {
+            Ok(None)
+        } else {
+            self.take_record().map(Some)
+        }
+    }
+
+    /// Takes the valid UTF-8 from the buffer.
+    /// A character that was split by the maximum record length is left in the buffer.
+    fn take_record(&mut self) -> Result<String, Error> {
+        let valid = match str::from_utf8(&self.buffer) {
+            Ok(_) => self.buffer.len(),
+            Err(e) if e.error_len().is_none() && e.valid_up_to() > 0 => e.valid_up_to(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let rest = self.buffer.split_off(valid);
+        let record = std::mem::replace(&mut self.buffer, rest);
+        Ok(String::from_utf8(record)?)
+    }
+}
+
+impl<R: Read> Provider for StreamProvider<R> {
+    /// Provides the next record.
+    /// This is an empty string if the end of the stream was reached (e.g. for empty input).
+    fn provide(&mut self) -> Result<String, Error> {
+        match self.next.take() {
+            Some(record) => Ok(record),
+            None => Ok(self.read_record()?.unwrap_or_default()),
+        }
+    }
+
+    fn has_more(&mut self) -> Result<bool, Error> {
+        if self.next.is_none() {
+            self.next = self.read_record()?;
+        }
+
+        Ok(self.next.is_some())
+    }
+}
+
+fn is_blank(bytes: &[u8]) -> bool {
+    bytes.iter().all(|b| b.is_ascii_whitespace())
+}
+
+// -------------------------------------------------------------------------------------------------
+
 /// A data provider that writes to standard output.
 pub struct StdoutConsumer {
     stream: Stdout,
@@ -70,16 +246,78 @@ impl StdoutConsumer {
 
 impl Consumer for StdoutConsumer {
     fn consume(&mut self, str: String) -> Result<(), Error> {
-        let bytes = str.as_bytes();
-        let mut offset = 0;
+        self.stream.write_all(str.as_bytes())?;
+        self.stream.flush()?;
+        Ok(())
+    }
+}
 
-        while offset < bytes.len() {
-            match self.stream.write(str.as_bytes()) {
-                Err(e) => return Err(e.into()),
-                Ok(n) => offset += n,
-            }
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads every record from a stream provider, the same way `main_loop` does.
+    fn records(input: &str, delimiter: RecordDelimiter, max_length: usize) -> Vec<String> {
+        let mut provider =
+            StreamProvider::new(input.as_bytes(), delimiter).with_max_length(max_length);
+        let mut records = vec![provider.provide().unwrap()];
+        while provider.has_more().unwrap() {
+            records.push(provider.provide().unwrap());
         }
 
-        Ok(())
+        records
+    }
+
+    #[test]
+    fn from_name() {
+        for name in DELIMITER_NAMES {
+            assert!(RecordDelimiter::from_name(name).is_some(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(records("", RecordDelimiter::Line, 64), vec![""]);
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(
+            records("a\nb\n", RecordDelimiter::Line, 64),
+            vec!["a\n", "b\n"]
+        );
+    }
+
+    #[test]
+    fn lines_without_final_newline() {
+        assert_eq!(
+            records("a\n\nb", RecordDelimiter::Line, 64),
+            vec!["a\n", "\n", "b"]
+        );
+    }
+
+    #[test]
+    fn paragraphs() {
+        assert_eq!(
+            records("\na\nb\n\n\nc", RecordDelimiter::Paragraph, 64),
+            vec!["\na\nb\n\n", "\nc"]
+        );
+    }
+
+    #[test]
+    fn nul() {
+        assert_eq!(
+            records("a\nb\0c\0", RecordDelimiter::Nul, 64),
+            vec!["a\nb\0", "c\0"]
+        );
+    }
+
+    #[test]
+    fn long_records_are_split_between_characters() {
+        let records = records("ab\u{00E9}\u{00E9}\n", RecordDelimiter::Line, 4);
+        assert_eq!(records.concat(), "ab\u{00E9}\u{00E9}\n");
+        assert!(records.iter().all(|r| r.len() <= 4), "{:?}", records);
     }
 }
@@ -0,0 +1,152 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use failure::Error;
+
+use crate::content::Consumer;
+// -------------------------------------------------------------------------------------------------
+
+/// What a `TeeConsumer` does when one of its consumers fails.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ErrorPolicy {
+    /// The error is returned immediately, and the remaining consumers are skipped.
+    FailFast,
+
+    /// The error is reported to the error handler, and the remaining consumers are still used.
+    BestEffort,
+}
+
+/// A data consumer that passes every string to several other consumers, in order.
+pub struct TeeConsumer {
+    consumers: Vec<(Box<dyn Consumer>, ErrorPolicy)>,
+    on_error: Option<Box<dyn FnMut(Error)>>,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl TeeConsumer {
+    pub fn new() -> Self {
+        TeeConsumer {
+            consumers: vec![],
+            on_error: None,
+        }
+    }
+
+    /// Adds a consumer.
+    ///
+    /// ## Arguments
+    /// - `consumer` - The consumer.
+    /// - `policy`   - What to do when the consumer fails.
+    pub fn with(mut self, consumer: Box<dyn Consumer>, policy: ErrorPolicy) -> Self {
+        self.consumers.push((consumer, policy));
+        self
+    }
+
+    /// Sets the function called with the errors of best-effort consumers.
+    /// Without one, those errors are ignored.
+    pub fn on_error<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(Error) + 'static,
+    {
+        self.on_error = Some(Box::new(handler));
+        self
+    }
+}
+
+impl Default for TeeConsumer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Consumer for TeeConsumer {
+    fn consume(&mut self, str: String) -> Result<(), Error> {
+        for (consumer, policy) in self.consumers.iter_mut() {
+            match consumer.consume(str.clone()) {
+                Ok(()) => {}
+                Err(error) => match policy {
+                    ErrorPolicy::FailFast => return Err(error),
+                    ErrorPolicy::BestEffort => {
+                        if let Some(ref mut handler) = self.on_error {
+                            handler(error);
+                        }
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use failure::err_msg;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Collector(Rc<RefCell<Vec<String>>>);
+    struct Failing;
+
+    impl Consumer for Collector {
+        fn consume(&mut self, str: String) -> Result<(), Error> {
+            self.0.borrow_mut().push(str);
+            Ok(())
+        }
+    }
+
+    impl Consumer for Failing {
+        fn consume(&mut self, _str: String) -> Result<(), Error> {
+            Err(err_msg("failed"))
+        }
+    }
+
+    #[test]
+    fn passes_strings_to_every_consumer() {
+        let first = Rc::new(RefCell::new(vec![]));
+        let second = Rc::new(RefCell::new(vec![]));
+        let mut tee = TeeConsumer::default()
+            .with(Box::new(Collector(first.clone())), ErrorPolicy::FailFast)
+            .with(Box::new(Collector(second.clone())), ErrorPolicy::FailFast);
+
+        tee.consume("a".to_owned()).unwrap();
+        tee.consume("b".to_owned()).unwrap();
+        assert_eq!(*first.borrow(), vec!["a", "b"]);
+        assert_eq!(*second.borrow(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn fail_fast_skips_remaining_consumers() {
+        let collected = Rc::new(RefCell::new(vec![]));
+        let mut tee = TeeConsumer::new()
+            .with(Box::new(Failing), ErrorPolicy::FailFast)
+            .with(
+                Box::new(Collector(collected.clone())),
+                ErrorPolicy::FailFast,
+            );
+
+        assert!(tee.consume("a".to_owned()).is_err());
+        assert!(collected.borrow().is_empty());
+    }
+
+    #[test]
+    fn best_effort_reports_errors_and_continues() {
+        let collected = Rc::new(RefCell::new(vec![]));
+        let errors = Rc::new(RefCell::new(vec![]));
+        let errors_handler = errors.clone();
+        let mut tee = TeeConsumer::new()
+            .with(Box::new(Failing), ErrorPolicy::BestEffort)
+            .with(
+                Box::new(Collector(collected.clone())),
+                ErrorPolicy::FailFast,
+            )
+            .on_error(move |error| errors_handler.borrow_mut().push(error.to_string()));
+
+        tee.consume("a".to_owned()).unwrap();
+        assert_eq!(*collected.borrow(), vec!["a"]);
+        assert_eq!(*errors.borrow(), vec!["failed"]);
+    }
+}
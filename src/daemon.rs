@@ -0,0 +1,368 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use failure::{err_msg, Error};
+
+use crate::service::{ProcessRequest, Service};
+// -------------------------------------------------------------------------------------------------
+
+/// The longest request line that will be accepted, in bytes.
+pub const MAX_LINE: usize = 1024 * 1024;
+
+/// The number of connections that are handled at the same time by default.
+pub const DEFAULT_WORKERS: usize = 8;
+
+/// A daemon that handles requests over a Unix domain socket.
+///
+/// Every line sent by a client is a JSON `ProcessRequest`, and is answered by a single line
+/// containing a JSON `ProcessResponse` (`{"text": "..."}`) or an error (`{"error": "..."}`).
+/// Clients can send any number of requests over one connection.
+pub struct Daemon {
+    listener: Arc<UnixListener>,
+    path: PathBuf,
+    service: Arc<Service>,
+    workers: usize,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl Daemon {
+    /// Creates a daemon listening on a socket.
+    /// A socket left behind by a daemon that is no longer running will be replaced, but any other
+    /// kind of file is left alone.
+    ///
+    /// ## Arguments
+    /// - `path`    - The path of the socket.
+    /// - `service` - The service that handles requests.
+    pub fn bind<P: AsRef<Path>>(path: P, service: Service) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let socket_error =
+            |e: io::Error| err_msg(format!("failed to bind to '{}': {}", path.display(), e));
+
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => {
+                if UnixStream::connect(path).is_ok() {
+                    return Err(err_msg(format!(
+                        "'{}' is already being used by another daemon",
+                        path.display()
+                    )));
+                }
+
+                fs::remove_file(path).map_err(socket_error)?;
+            }
+
+            Ok(_) => {
+                return Err(err_msg(format!(
+                    "'{}' already exists and is not a socket",
+                    path.display()
+                )));
+            }
+
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(socket_error(e)),
+        }
+
+        let listener = bind_private(path).map_err(socket_error)?;
+        Ok(Daemon {
+            listener: Arc::new(listener),
+            path: path.to_owned(),
+            service: Arc::new(service),
+            workers: DEFAULT_WORKERS,
+        })
+    }
+
+    /// Sets the number of connections that are handled at the same time.
+    /// Other connections wait until a worker is free.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Gets the path of the socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Handles connections until the daemon fails.
+    /// Connections are handled by a fixed number of worker threads (see `with_workers`).
+    pub fn run(&self) -> Result<(), Error> {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..self.workers {
+            let listener = Arc::clone(&self.listener);
+            let service = Arc::clone(&self.service);
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let error = loop {
+                    match listener.accept() {
+                        // A client that disconnects early is not a daemon failure.
+                        Ok((stream, _)) => {
+                            let _ = handle(&service, stream);
+                        }
+                        Err(error) => break error,
+                    }
+                };
+
+                let _ = sender.send(error);
+            });
+        }
+
+        drop(sender);
+        match receiver.recv() {
+            Ok(error) => Err(error.into()),
+            Err(_) => Err(err_msg("every daemon worker stopped unexpectedly")),
+        }
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        // Something else may have replaced the socket since it was created.
+        match fs::symlink_metadata(&self.path) {
+            Ok(metadata) if metadata.file_type().is_socket() => {
+                let _ = fs::remove_file(&self.path);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Gets the default path of the socket.
+///
+/// This is `$XDG_RUNTIME_DIR/joinerator.sock`, or `joinerator-$USER.sock` in the temporary
+/// directory if there is no runtime directory.
+pub fn default_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir.join("joinerator.sock"),
+        _ => {
+            let user = env::var("USER").unwrap_or_else(|_| "default".to_owned());
+            env::temp_dir().join(format!("joinerator-{}.sock", user))
+        }
+    }
+}
+
+/// Binds a socket that only the current user can connect to.
+///
+/// The socket is created inside a new directory that only the current user can access, and is
+/// linked to its path once its permissions are restricted. Linking fails instead of replacing
+/// anything created at the path in the meantime.
+fn bind_private(path: &Path) -> io::Result<UnixListener> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut attempt = 0;
+    let dir = loop {
+        let dir = parent.join(format!(
+            ".{}.{}.{}",
+            name.to_string_lossy(),
+            std::process::id(),
+            attempt
+        ));
+
+        match fs::DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(()) => break dir,
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    };
+
+    let private = dir.join("socket");
+    let result = UnixListener::bind(&private).and_then(|listener| {
+        fs::set_permissions(&private, fs::Permissions::from_mode(0o600))?;
+        fs::hard_link(&private, path)?;
+        Ok(listener)
+    });
+
+    let _ = fs::remove_file(&private);
+    let _ = fs::remove_dir(&dir);
+    result
+}
+
+fn handle(service: &Service, stream: UnixStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut line = vec![];
+
+    loop {
+        line.clear();
+        (&mut reader)
+            .take(MAX_LINE as u64 + 1)
+            .read_until(b'\n', &mut line)?;
+
+        if line.is_empty() {
+            return Ok(());
+        }
+
+        let response = if line.len() > MAX_LINE && line.last() != Some(&b'\n') {
+            // The rest of the line can't be understood, so the connection is closed.
+            let error = format!("request is longer than {} bytes", MAX_LINE);
+            writeln!(writer, "{}", error_json(&error))?;
+            return Ok(());
+        } else if line.iter().all(|b| b.is_ascii_whitespace()) {
+            continue;
+        } else {
+            respond(service, &line)
+        };
+
+        writeln!(writer, "{}", response)?;
+        writer.flush()?;
+    }
+}
+
+fn respond(service: &Service, line: &[u8]) -> String {
+    let result = serde_json::from_slice::<ProcessRequest>(line)
+        .map_err(Error::from)
+        .and_then(|request| service.process(&request))
+        .and_then(|response| serde_json::to_string(&response).map_err(Error::from));
+
+    match result {
+        Ok(json) => json,
+        Err(error) => error_json(&error.to_string()),
+    }
+}
+
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Settings};
+    use crate::repertoire::RepertoireSet;
+
+    fn service() -> Service {
+        Service::new(
+            RepertoireSet::with_builtin(),
+            Config::default(),
+            Settings::default(),
+        )
+    }
+
+    fn socket_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("joinerator-{}-{}.sock", name, std::process::id()))
+    }
+
+    /// Starts a daemon, and sends it some data.
+    ///
+    /// ## Returns
+    /// The response lines.
+    fn send(name: &str, data: &[u8]) -> Vec<serde_json::Value> {
+        let path = socket_path(name);
+        let daemon = Daemon::bind(&path, service()).unwrap();
+        thread::spawn(move || daemon.run());
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        // The daemon closes the connection after a line that is too long, which can interrupt
+        // writing the rest of the data.
+        let _ = stream.write_all(data);
+        let _ = stream.shutdown(std::net::Shutdown::Write);
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let _ = fs::remove_file(&path);
+
+        response
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn answers_every_line() {
+        let responses = send(
+            "lines",
+            b"{\"text\": \"a\", \"settings\": {\"transform\": [\"uppercase\"], \"limit\": 0}}\n\n\
+              not json\n\
+              {\"text\": \"b\", \"settings\": {\"generators\": [\"above:1:17\"]}}\n\
+              {\"text\": \"c\", \"settings\": {\"limit\": 0}}",
+        );
+
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0]["text"], "A");
+        assert!(responses[1]["error"].is_string());
+        assert!(responses[2]["error"].is_string());
+        assert_eq!(responses[3]["text"], "c");
+    }
+
+    #[test]
+    fn more_connections_than_workers() {
+        let path = socket_path("workers");
+        let daemon = Daemon::bind(&path, service()).unwrap().with_workers(2);
+        thread::spawn(move || daemon.run());
+
+        let clients: Vec<_> = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    let mut stream = UnixStream::connect(&path).unwrap();
+                    writeln!(stream, r#"{{"text": "{}", "settings": {{"limit": 0}}}}"#, i).unwrap();
+                    stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+                    let mut response = String::new();
+                    stream.read_to_string(&mut response).unwrap();
+                    serde_json::from_str::<serde_json::Value>(&response).unwrap()
+                })
+            })
+            .collect();
+
+        for (i, client) in clients.into_iter().enumerate() {
+            assert_eq!(client.join().unwrap()["text"], i.to_string());
+        }
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn rejects_long_lines() {
+        let mut data = vec![b'a'; MAX_LINE + 1];
+        data.extend_from_slice(b"\n{\"text\": \"ignored\"}\n");
+
+        let responses = send("long", &data);
+        assert_eq!(responses.len(), 1);
+        assert!(responses[0]["error"]
+            .as_str()
+            .unwrap()
+            .contains("request is longer"));
+    }
+
+    #[test]
+    fn bind_replaces_stale_sockets() {
+        let path = socket_path("stale");
+        drop(UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let daemon = Daemon::bind(&path, service()).unwrap();
+        assert!(Daemon::bind(&path, service()).is_err());
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        drop(daemon);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn bind_keeps_other_files() {
+        let path = socket_path("regular");
+        fs::write(&path, "important").unwrap();
+
+        assert!(Daemon::bind(&path, service()).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "important");
+        fs::remove_file(&path).unwrap();
+    }
+}
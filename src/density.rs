@@ -0,0 +1,186 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use std::f32::consts::PI;
+// -------------------------------------------------------------------------------------------------
+
+/// A function describing how likely each position of a string is to receive combining glyphs.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum Density {
+    /// Every position is equally likely.
+    #[default]
+    Uniform,
+
+    /// Positions become more likely towards the end of the string.
+    RampUp,
+
+    /// Positions become less likely towards the end of the string.
+    RampDown,
+
+    /// Positions pulse between likely and unlikely, repeating `periods` times across the string.
+    Wave { periods: f32 },
+
+    /// Positions in the center of the string are the most likely.
+    Center,
+
+    /// Positions at the edges of the string are the most likely.
+    Edges,
+
+    /// Evenly-spaced control points across the string, interpolated linearly.
+    /// Each point is a weight, where `0.0` means the position will never be picked.
+    Points(Vec<f32>),
+}
+
+/// The names of every predefined density that can be parsed with `Density::parse`.
+pub const NAMES: &[&str] = &["uniform", "ramp-up", "ramp-down", "wave", "center", "edges"];
+
+// -------------------------------------------------------------------------------------------------
+
+impl Density {
+    /// Parses a density from a string.
+    ///
+    /// This accepts one of `NAMES`, `wave:PERIODS`, or a comma-separated list of control points
+    /// (e.g. `0,1,0.2`).
+    ///
+    /// ## Arguments
+    /// - `str` - The string to parse.
+    pub fn parse(str: &str) -> Option<Self> {
+        match str {
+            "uniform" => return Some(Density::Uniform),
+            "ramp-up" | "ramp" => return Some(Density::RampUp),
+            "ramp-down" => return Some(Density::RampDown),
+            "wave" => return Some(Density::Wave { periods: 1.0 }),
+            "center" => return Some(Density::Center),
+            "edges" => return Some(Density::Edges),
+            _ => {}
+        }
+
+        if let Some(periods) = str.strip_prefix("wave:") {
+            return match periods.parse::<f32>() {
+                Ok(periods) if periods > 0.0 => Some(Density::Wave { periods }),
+                _ => None,
+            };
+        }
+
+        let points: Result<Vec<f32>, _> = str.split(',').map(|v| v.trim().parse::<f32>()).collect();
+        match points {
+            Ok(ref points) if points.iter().any(|p| *p < 0.0 || !p.is_finite()) => None,
+            Ok(ref points) if !points.iter().any(|p| *p > 0.0) => None,
+            Ok(points) => Some(Density::Points(points)),
+            Err(_) => None,
+        }
+    }
+
+    /// Calculates the weight of a position.
+    ///
+    /// ## Arguments
+    /// - `t` - The relative position in the string, from `0.0` (start) to `1.0` (end).
+    ///
+    /// ## Returns
+    /// A non-negative weight, where `0.0` means the position will never be picked.
+    pub fn weight(&self, t: f32) -> f32 {
+        match self {
+            Density::Uniform => 1.0,
+            Density::RampUp => t,
+            Density::RampDown => 1.0 - t,
+            Density::Wave { periods } => 0.5 - 0.5 * (2.0 * PI * periods * t).cos(),
+            Density::Center => 1.0 - (2.0 * t - 1.0).abs(),
+            Density::Edges => (2.0 * t - 1.0).abs(),
+            Density::Points(points) => {
+                if points.len() == 1 {
+                    return points[0];
+                }
+
+                let scaled = t * ((points.len() - 1) as f32);
+                let index = (scaled.floor() as usize).min(points.len() - 2);
+                let fraction = scaled - (index as f32);
+                points[index] + (points[index + 1] - points[index]) * fraction
+            }
+        }
+    }
+
+    /// Calculates the weight of every position in a string.
+    ///
+    /// ## Arguments
+    /// - `length` - The number of positions in the string.
+    pub fn weights(&self, length: usize) -> Vec<f32> {
+        (0..length)
+            .map(|i| {
+                if length < 2 {
+                    0.5
+                } else {
+                    (i as f32) / ((length - 1) as f32)
+                }
+            })
+            .map(|t| self.weight(t).max(0.0))
+            .collect()
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_weights(density: &Density, expected: &[f32]) {
+        let weights = density.weights(expected.len());
+        for (weight, expected) in weights.iter().zip(expected) {
+            assert!((weight - expected).abs() < 1e-5, "{:?}", weights);
+        }
+    }
+
+    #[test]
+    fn parse_names() {
+        for name in NAMES {
+            assert!(Density::parse(name).is_some(), "{}", name);
+        }
+
+        assert_eq!(Density::parse("ramp"), Some(Density::RampUp));
+        assert_eq!(Density::parse("wave"), Some(Density::Wave { periods: 1.0 }));
+        assert_eq!(Density::default(), Density::Uniform);
+    }
+
+    #[test]
+    fn parse_wave() {
+        assert_eq!(
+            Density::parse("wave:2.5"),
+            Some(Density::Wave { periods: 2.5 })
+        );
+        assert_eq!(Density::parse("wave:0"), None);
+        assert_eq!(Density::parse("wave:x"), None);
+    }
+
+    #[test]
+    fn parse_points() {
+        assert_eq!(
+            Density::parse("0, 1,0.2"),
+            Some(Density::Points(vec![0.0, 1.0, 0.2]))
+        );
+        assert_eq!(Density::parse("0,0"), None);
+        assert_eq!(Density::parse("1,-1"), None);
+        assert_eq!(Density::parse("1,inf"), None);
+        assert_eq!(Density::parse("sideways"), None);
+    }
+
+    #[test]
+    fn weights() {
+        assert_weights(&Density::Uniform, &[1.0, 1.0, 1.0]);
+        assert_weights(&Density::RampUp, &[0.0, 0.5, 1.0]);
+        assert_weights(&Density::RampDown, &[1.0, 0.5, 0.0]);
+        assert_weights(&Density::Wave { periods: 1.0 }, &[0.0, 1.0, 0.0]);
+        assert_weights(&Density::Center, &[0.0, 1.0, 0.0]);
+        assert_weights(&Density::Edges, &[1.0, 0.0, 1.0]);
+        assert_weights(
+            &Density::Points(vec![0.0, 1.0]),
+            &[0.0, 0.25, 0.5, 0.75, 1.0],
+        );
+        assert_weights(&Density::Points(vec![0.3]), &[0.3, 0.3]);
+    }
+
+    #[test]
+    fn weights_of_short_strings() {
+        assert_weights(&Density::RampUp, &[0.5]);
+        assert!(Density::RampUp.weights(0).is_empty());
+    }
+}
@@ -0,0 +1,670 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{cursor, execute, queue};
+use failure::{err_msg, Error};
+
+use crate::config::{self, Settings};
+use crate::density::Density;
+use crate::joinerator::{GeneratorFrequency, GeneratorOptions};
+use crate::repertoire::GlyphPosition;
+use crate::service::{ProcessRequest, Service};
+use crate::transform;
+
+#[cfg(feature = "clipboard_support")]
+use crate::content::{clipboard::ClipboardConsumer, Consumer};
+// -------------------------------------------------------------------------------------------------
+
+/// How much the percentage frequency of a generator changes with each key press.
+const PERCENT_STEP: f64 = 5.0;
+
+/// The largest stacking size that can be chosen.
+const MAX_STACKING: usize = 16;
+
+/// An interactive terminal interface for tuning settings.
+///
+/// The user types the text, and adjusts the repertoire, seed, transformer chain, and every
+/// generator while the output is rendered live. The settings can be saved as a preset, and the
+/// output can be copied to the clipboard.
+pub struct Interactive {
+    service: Service,
+    repertoires: Vec<String>,
+    transformers: Vec<&'static str>,
+
+    text: String,
+    output: Result<String, String>,
+    status: String,
+    selected: usize,
+    preset_name: Option<String>,
+
+    repertoire: usize,
+    seed: u64,
+    transform: Vec<String>,
+    candidate: usize,
+    generators: Vec<Generator>,
+}
+
+/// A generator that can be adjusted.
+#[derive(Debug, PartialEq, Clone)]
+struct Generator {
+    position: GlyphPosition,
+    frequency: Frequency,
+    stacking: usize,
+
+    /// The density from the generator spec, if it had one.
+    /// This is kept as written, since the interface can't adjust it.
+    density: Option<String>,
+}
+
+/// A setting that can be selected and adjusted.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Field {
+    Repertoire,
+    Seed,
+    Transform,
+    Frequency(usize),
+    Stacking(usize),
+}
+
+/// The frequency of a generator.
+/// Unlike `GeneratorFrequency`, this can be zero to disable the generator.
+/// Percentages are kept exactly as they were parsed, so unedited generators are saved unchanged.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Frequency {
+    Percent(f64),
+    Fixed(usize),
+}
+
+/// Puts the terminal into raw mode and the alternate screen until it is dropped.
+struct TerminalGuard;
+
+// -------------------------------------------------------------------------------------------------
+
+impl Interactive {
+    /// Creates the interactive interface.
+    /// The initial settings are taken from the defaults of the service.
+    ///
+    /// ## Arguments
+    /// - `service` - The service that renders the output.
+    /// - `text`    - The initial text.
+    pub fn new(service: Service, text: String) -> Self {
+        let defaults = service.defaults().clone();
+        let repertoires: Vec<String> = service.repertoires().into_iter().map(|r| r.name).collect();
+        let repertoire = defaults
+            .repertoire
+            .as_ref()
+            .and_then(|name| repertoires.iter().position(|r| r == name))
+            .unwrap_or(0);
+
+        let mut interactive = Interactive {
+            service,
+            repertoires,
+            transformers: transform::NAMES.to_vec(),
+
+            text,
+            output: Ok(String::new()),
+            status: String::new(),
+            selected: 0,
+            preset_name: None,
+
+            repertoire,
+            seed: defaults
+                .seed
+                .unwrap_or_else(|| u64::from(rand::random::<u16>())),
+            transform: defaults.transform.clone().unwrap_or_default(),
+            candidate: 0,
+            generators: initial_generators(&defaults),
+        };
+
+        interactive.render_output();
+        interactive
+    }
+
+    /// Runs the interface until the user quits.
+    pub fn run(&mut self) -> Result<(), Error> {
+        let _guard = TerminalGuard::new()?;
+        let mut stdout = io::stdout();
+
+        loop {
+            self.draw(&mut stdout)?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Gets the settings chosen by the user, merged on top of the defaults of the service.
+    /// These are the settings used to render the output. Generators that are disabled are left out.
+    pub fn settings(&self) -> Settings {
+        let generators = self
+            .generators
+            .iter()
+            .filter(|generator| generator.frequency.is_enabled() && generator.stacking > 0)
+            .map(Generator::spec)
+            .collect();
+
+        let mut settings = self.service.defaults().clone();
+        settings.merge(&Settings {
+            repertoire: Some(self.repertoires[self.repertoire].clone()),
+            seed: Some(self.seed),
+            transform: Some(self.transform.clone()),
+            generators: Some(generators),
+            ..Settings::default()
+        });
+
+        settings
+    }
+
+    /// Gets the rendered output, or the reason it could not be rendered.
+    pub fn output(&self) -> Result<&str, &str> {
+        self.output
+            .as_ref()
+            .map(|s| s.as_str())
+            .map_err(|s| s.as_str())
+    }
+
+    fn fields(&self) -> Vec<Field> {
+        let mut fields = vec![Field::Repertoire, Field::Seed, Field::Transform];
+        for index in 0..self.generators.len() {
+            fields.push(Field::Frequency(index));
+            fields.push(Field::Stacking(index));
+        }
+
+        fields
+    }
+
+    fn render_output(&mut self) {
+        let request = ProcessRequest {
+            text: self.text.clone(),
+            preset: None,
+            settings: self.settings(),
+        };
+
+        self.output = self
+            .service
+            .process(&request)
+            .map(|response| response.text)
+            .map_err(|e| e.to_string());
+    }
+
+    /// Handles a key press.
+    ///
+    /// ## Returns
+    /// False if the user quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        // Naming a preset.
+        if let Some(ref mut name) = self.preset_name {
+            match key.code {
+                KeyCode::Esc => self.preset_name = None,
+                KeyCode::Backspace => {
+                    name.pop();
+                }
+                KeyCode::Enter => {
+                    let name = self.preset_name.take().unwrap();
+                    self.status = match self.save_preset(&name) {
+                        Ok(path) => format!("Saved preset '{}' to {}", name, path.display()),
+                        Err(error) => error.to_string(),
+                    };
+                }
+                KeyCode::Char(c) if !control => name.push(c),
+                _ => {}
+            }
+
+            return true;
+        }
+
+        let field = self.fields()[self.selected];
+        self.status.clear();
+        match key.code {
+            KeyCode::Esc => return false,
+            KeyCode::Char('c') | KeyCode::Char('q') if control => return false,
+            KeyCode::Char('s') if control => self.preset_name = Some(String::new()),
+            KeyCode::Char('y') if control => self.copy(),
+            KeyCode::Char('r') if control => self.seed = u64::from(rand::random::<u16>()),
+
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(self.fields().len() - 1),
+            KeyCode::Left => self.adjust(field, false),
+            KeyCode::Right => self.adjust(field, true),
+            KeyCode::Char('t') if control => self.add_transformer(),
+            KeyCode::Insert => self.add_transformer(),
+            KeyCode::Delete => {
+                self.transform.pop();
+            }
+
+            KeyCode::Enter => self.text.push('\n'),
+            KeyCode::Backspace => {
+                self.text.pop();
+            }
+            KeyCode::Char(c) if !control => self.text.push(c),
+            _ => return true,
+        }
+
+        self.render_output();
+        true
+    }
+
+    fn add_transformer(&mut self) {
+        self.transform
+            .push(self.transformers[self.candidate].to_owned());
+    }
+
+    fn adjust(&mut self, field: Field, increase: bool) {
+        let step = |value: usize, max: usize| {
+            if increase {
+                (value + 1).min(max)
+            } else {
+                value.saturating_sub(1)
+            }
+        };
+
+        match field {
+            Field::Repertoire => {
+                self.repertoire = step(self.repertoire, self.repertoires.len() - 1);
+            }
+            Field::Seed => {
+                self.seed = if increase {
+                    self.seed.wrapping_add(1)
+                } else {
+                    self.seed.wrapping_sub(1)
+                };
+            }
+            Field::Transform => {
+                self.candidate = step(self.candidate, self.transformers.len() - 1);
+            }
+            Field::Frequency(index) => {
+                let frequency = &mut self.generators[index].frequency;
+                *frequency = match *frequency {
+                    Frequency::Percent(percent) if increase => {
+                        Frequency::Percent((percent + PERCENT_STEP).min(100.0))
+                    }
+                    Frequency::Percent(percent) => {
+                        Frequency::Percent((percent - PERCENT_STEP).max(0.0))
+                    }
+                    Frequency::Fixed(count) => Frequency::Fixed(step(count, usize::MAX)),
+                };
+            }
+            Field::Stacking(index) => {
+                let stacking = &mut self.generators[index].stacking;
+                *stacking = step(*stacking, MAX_STACKING);
+            }
+        }
+    }
+
+    #[cfg(feature = "clipboard_support")]
+    fn copy(&mut self) {
+        let output = match self.output {
+            Ok(ref output) => output.clone(),
+            Err(_) => return,
+        };
+
+        self.status = match ClipboardConsumer::new().consume(output) {
+            Ok(()) => "Copied the output to the clipboard.".to_owned(),
+            Err(error) => error.to_string(),
+        };
+    }
+
+    #[cfg(not(feature = "clipboard_support"))]
+    fn copy(&mut self) {
+        self.status = "Clipboard support is not enabled.".to_owned();
+    }
+
+    /// Saves the settings as a preset in the user's configuration file.
+    fn save_preset(&self, name: &str) -> Result<PathBuf, Error> {
+        if name.is_empty() {
+            return Err(err_msg("the preset needs a name"));
+        }
+
+        let path = config::user_path()
+            .ok_or_else(|| err_msg("the configuration directory could not be found"))?;
+
+        config::save_preset(&path, name, &self.settings())?;
+        Ok(path)
+    }
+
+    fn draw<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut row = 0;
+        let mut line = |out: &mut W, text: &str| -> io::Result<()> {
+            queue!(
+                out,
+                cursor::MoveTo(0, row),
+                Clear(ClearType::CurrentLine),
+                Print(text)
+            )?;
+            row += 1;
+            Ok(())
+        };
+
+        queue!(out, Clear(ClearType::All))?;
+        line(out, "Text:")?;
+        for text in self.text.split('\n') {
+            line(out, &format!("  {}", text))?;
+        }
+
+        line(out, "")?;
+        match self.output {
+            Ok(ref output) => {
+                line(out, "Output:")?;
+                for text in output.split('\n') {
+                    line(out, &format!("  {}", text))?;
+                }
+            }
+            Err(ref error) => line(out, &format!("Error: {}", error))?,
+        }
+
+        line(out, "")?;
+        for (index, field) in self.fields().into_iter().enumerate() {
+            let marker = if index == self.selected { ">" } else { " " };
+            let (name, value) = self.describe(field);
+            line(out, &format!("{} {:24} {}", marker, name, value))?;
+        }
+
+        line(out, "")?;
+        match self.preset_name {
+            Some(ref name) => line(out, &format!("Preset name: {}", name))?,
+            None => line(out, &self.status)?,
+        }
+
+        queue!(out, SetAttribute(Attribute::Dim))?;
+        line(
+            out,
+            "Up/Down: select  Left/Right: adjust  Ctrl-T: add transformer  Delete: remove \
+             transformer  Ctrl-R: random seed  Ctrl-S: save preset  Ctrl-Y: copy  Esc: quit",
+        )?;
+        queue!(out, SetAttribute(Attribute::Reset))?;
+
+        out.flush()
+    }
+
+    fn describe(&self, field: Field) -> (String, String) {
+        match field {
+            Field::Repertoire => (
+                "repertoire".to_owned(),
+                self.repertoires[self.repertoire].clone(),
+            ),
+            Field::Seed => ("seed".to_owned(), self.seed.to_string()),
+            Field::Transform => (
+                "transform".to_owned(),
+                format!(
+                    "[{}]  add: {}",
+                    self.transform.join(", "),
+                    self.transformers[self.candidate]
+                ),
+            ),
+            Field::Frequency(index) => {
+                let generator = &self.generators[index];
+                (
+                    format!("{} frequency", generator.name()),
+                    generator.frequency.to_string(),
+                )
+            }
+            Field::Stacking(index) => {
+                let generator = &self.generators[index];
+                (
+                    format!("{} stacking", generator.name()),
+                    generator.stacking.to_string(),
+                )
+            }
+        }
+    }
+}
+
+impl Generator {
+    fn disabled(position: GlyphPosition) -> Self {
+        Generator {
+            position,
+            frequency: Frequency::Percent(0.0),
+            stacking: 1,
+            density: None,
+        }
+    }
+
+    /// Gets the name shown for the generator.
+    fn name(&self) -> String {
+        match self.density {
+            Some(ref density) => format!("{} ({})", self.position.name(), density),
+            None => self.position.name().to_owned(),
+        }
+    }
+
+    /// Gets the generator as a `POSITION:FREQUENCY:STACKING[:DENSITY]` spec.
+    fn spec(&self) -> String {
+        let spec = format!(
+            "{}:{}:{}",
+            self.position.name(),
+            self.frequency,
+            self.stacking
+        );
+
+        match self.density {
+            Some(ref density) => format!("{}:{}", spec, density),
+            None => spec,
+        }
+    }
+}
+
+impl Frequency {
+    /// Parses a frequency in the same format as `GeneratorFrequency::parse`.
+    fn parse(str: &str) -> Option<Self> {
+        GeneratorFrequency::parse(str)?;
+        match str.strip_suffix('%') {
+            Some(percent) => percent.parse::<f64>().ok().map(Frequency::Percent),
+            None => str.parse::<usize>().ok().map(Frequency::Fixed),
+        }
+    }
+
+    fn is_enabled(self) -> bool {
+        match self {
+            Frequency::Percent(percent) => percent > 0.0,
+            Frequency::Fixed(count) => count > 0,
+        }
+    }
+}
+
+impl std::fmt::Display for Frequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Frequency::Percent(percent) => write!(f, "{}%", percent),
+            Frequency::Fixed(count) => write!(f, "{}", count),
+        }
+    }
+}
+
+impl TerminalGuard {
+    fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Gets the generators from the settings, in order.
+/// Every position without a generator gets a disabled one, so that it can be enabled.
+fn initial_generators(settings: &Settings) -> Vec<Generator> {
+    let mut generators = vec![];
+    match settings.generators {
+        Some(ref specs) => {
+            for spec in specs {
+                let options = GeneratorOptions::parse(spec, &Density::default());
+                let frequency = spec.split(':').nth(1).and_then(Frequency::parse);
+                if let (Some(options), Some(frequency)) = (options, frequency) {
+                    generators.push(Generator {
+                        position: options.category,
+                        frequency,
+                        stacking: options.stacking,
+                        density: spec.splitn(4, ':').nth(3).map(|d| d.to_owned()),
+                    });
+                }
+            }
+        }
+        None => {
+            for (name, generator) in settings.generator.iter() {
+                let position = GlyphPosition::from_name(name);
+                let frequency = generator
+                    .frequency
+                    .as_ref()
+                    .map_or(Some(Frequency::Percent(100.0)), |f| Frequency::parse(f));
+
+                if let (Some(position), Some(frequency)) = (position, frequency) {
+                    generators.push(Generator {
+                        position,
+                        frequency,
+                        stacking: generator.stacking.unwrap_or(1),
+                        density: None,
+                    });
+                }
+            }
+        }
+    }
+
+    for &position in GlyphPosition::ALL.iter() {
+        if !generators.iter().any(|g| g.position == position) {
+            generators.push(Generator::disabled(position));
+        }
+    }
+
+    generators
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, GeneratorSettings};
+    use crate::repertoire::RepertoireSet;
+    use std::collections::BTreeMap;
+
+    fn interactive(defaults: Settings) -> Interactive {
+        let service = Service::new(RepertoireSet::with_builtin(), Config::default(), defaults);
+        Interactive::new(service, "Hello".to_owned())
+    }
+
+    #[test]
+    fn settings_include_the_defaults() {
+        let mut generator = BTreeMap::new();
+        generator.insert(
+            "above".to_owned(),
+            GeneratorSettings {
+                frequency: Some("50%".to_owned()),
+                stacking: Some(2),
+            },
+        );
+
+        let interactive = interactive(Settings {
+            seed: Some(3),
+            limit: Some(0),
+            format: Some("markdown".to_owned()),
+            transform: Some(vec!["uppercase".to_owned()]),
+            generator,
+            ..Settings::default()
+        });
+
+        let settings = interactive.settings();
+        assert_eq!(settings.seed, Some(3));
+        assert_eq!(settings.limit, Some(0));
+        assert_eq!(settings.format, Some("markdown".to_owned()));
+        assert!(settings.generator.is_empty());
+        assert!(settings
+            .generators
+            .unwrap()
+            .contains(&"above:50%:2".to_owned()));
+        assert_eq!(interactive.output(), Ok("HELLO"));
+    }
+
+    #[test]
+    fn settings_keep_densities_and_duplicate_positions() {
+        let specs = vec![
+            "below:30%:2:edges".to_owned(),
+            "below:5:1".to_owned(),
+            "above:50%:1:wave:2".to_owned(),
+        ];
+
+        let interactive = interactive(Settings {
+            generators: Some(specs.clone()),
+            ..Settings::default()
+        });
+
+        assert_eq!(interactive.settings().generators, Some(specs));
+    }
+
+    #[test]
+    fn settings_keep_fractional_percentages() {
+        let specs = vec!["above:12.5%:1".to_owned(), "below:0.3%:2".to_owned()];
+        let interactive = interactive(Settings {
+            generators: Some(specs.clone()),
+            ..Settings::default()
+        });
+
+        assert_eq!(interactive.settings().generators, Some(specs));
+    }
+
+    fn press(interactive: &mut Interactive, code: KeyCode) -> bool {
+        interactive.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn keys_stay_in_range() {
+        let mut interactive = interactive(Settings::default());
+        press(&mut interactive, KeyCode::Up);
+        assert_eq!(interactive.selected, 0);
+
+        // The first generator starts disabled.
+        interactive.selected = 3;
+        assert_eq!(interactive.fields()[3], Field::Frequency(0));
+        press(&mut interactive, KeyCode::Left);
+        assert_eq!(interactive.generators[0].frequency, Frequency::Percent(0.0));
+
+        for _ in 0..30 {
+            press(&mut interactive, KeyCode::Right);
+        }
+        assert_eq!(
+            interactive.generators[0].frequency,
+            Frequency::Percent(100.0)
+        );
+
+        press(&mut interactive, KeyCode::Down);
+        for _ in 0..MAX_STACKING + 5 {
+            press(&mut interactive, KeyCode::Right);
+        }
+        assert_eq!(interactive.generators[0].stacking, MAX_STACKING);
+
+        let last = interactive.fields().len() - 1;
+        interactive.selected = last;
+        press(&mut interactive, KeyCode::Down);
+        assert_eq!(interactive.selected, last);
+    }
+
+    #[test]
+    fn keys_edit_the_text_and_quit() {
+        let mut interactive = interactive(Settings {
+            limit: Some(0),
+            ..Settings::default()
+        });
+
+        assert!(press(&mut interactive, KeyCode::Char('!')));
+        assert_eq!(interactive.output(), Ok("Hello!"));
+        assert!(press(&mut interactive, KeyCode::Backspace));
+        assert_eq!(interactive.output(), Ok("Hello"));
+
+        assert!(!press(&mut interactive, KeyCode::Esc));
+        assert!(!interactive.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+    }
+}
@@ -4,43 +4,265 @@
 use std::cell::Cell;
 use std::collections::HashMap;
 
+use crate::density::Density;
+use crate::limit::LimitUnit;
+use crate::normalize::{self, Normalization};
+use crate::protect::{self, Protector};
 use crate::repertoire::{Glyph, GlyphPosition, Repertoire};
-use core::borrow::BorrowMut;
-use rand::rngs::ThreadRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
-use std::cmp::min;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use unicode_segmentation::UnicodeSegmentation;
 // -------------------------------------------------------------------------------------------------
 
+/// A generator that stylizes text by adding Unicode combining glyphs to it.
 pub struct Joinerator<'a> {
     pub options: Options<'a>,
     glyphs: HashMap<GlyphPosition, Vec<Glyph>>,
-    rng: ThreadRng,
+    rng: ChaChaRng,
 }
 
+/// The options used to create a `Joinerator`.
+/// These can be created with `Options::builder`.
 pub struct Options<'a> {
+    /// Allows combining glyphs to be added to characters they were not designed for.
     pub allow_unreadable: bool,
+
+    /// The maximum length of the processed string, measured in `limit_unit`.
     pub limit: Option<usize>,
 
+    /// The unit used to measure the length of the processed string.
+    pub limit_unit: LimitUnit,
+
+    /// The seed for the random number generator.
+    /// Using the same seed, input, and options will always produce the same output.
+    pub seed: Option<u64>,
+
+    /// The repertoire of combining glyphs.
     pub repertoire: &'a Repertoire,
+
+    /// The options for each category of combining glyphs to add.
     pub generator: Vec<GeneratorOptions>,
+
+    /// The detectors for spans of text that will never have combining glyphs added.
+    /// Protected text is not counted when calculating percentage frequencies.
+    pub protect: Vec<Protector>,
+
+    /// Sorts the combining glyphs of every character by their canonical combining class.
+    /// Combining glyphs that would be replaced by normalization (e.g. U+0340) are never added.
+    pub canonical_order: bool,
+
+    /// The normalization form applied to the input and output.
+    /// This implies `canonical_order`.
+    pub normalize: Option<Normalization>,
+}
+
+/// A builder for `Options`.
+pub struct OptionsBuilder<'a> {
+    options: Options<'a>,
 }
 
+/// The options for generating a single category of combining glyphs.
 pub struct GeneratorOptions {
+    /// The position of the combining glyphs.
     pub category: GlyphPosition,
+
+    /// How many characters will have combining glyphs added to them.
     pub frequency: GeneratorFrequency,
+
+    /// The maximum number of combining glyphs that can be stacked on a single character.
     pub stacking: usize,
+
+    /// How likely each position of the string is to be picked.
+    pub density: Density,
 }
 
+/// The frequency of a generator.
 pub enum GeneratorFrequency {
+    /// A percentage of the characters, from `0.0` to `1.0`.
     Percentage(f32),
+
+    /// A fixed number of characters.
     Fixed(usize),
 }
 
 // -------------------------------------------------------------------------------------------------
 
+impl<'a> Options<'a> {
+    /// Creates a builder for options using a specific repertoire.
+    /// The builder starts with no generators, no limit, and no seed.
+    ///
+    /// ## Arguments
+    /// - `repertoire` - The repertoire of combining glyphs.
+    pub fn builder(repertoire: &'a Repertoire) -> OptionsBuilder<'a> {
+        OptionsBuilder {
+            options: Options {
+                allow_unreadable: false,
+                limit: None,
+                limit_unit: LimitUnit::default(),
+                seed: None,
+                repertoire,
+                generator: vec![],
+                protect: vec![],
+                canonical_order: false,
+                normalize: None,
+            },
+        }
+    }
+}
+
+impl GeneratorFrequency {
+    /// Parses a generator frequency.
+    /// This is either a percentage (e.g. `60%`) or a fixed number of characters (e.g. `10`).
+    ///
+    /// ## Arguments
+    /// - `str` - The string to parse.
+    pub fn parse(str: &str) -> Option<Self> {
+        if let Some(percent) = str.strip_suffix('%') {
+            match percent.parse::<f32>() {
+                Ok(v) if v > 0.0 && v <= 100.0 => Some(GeneratorFrequency::Percentage(v / 100.0)),
+                _ => None,
+            }
+        } else {
+            match str.parse::<usize>() {
+                Ok(v) if v > 0 => Some(GeneratorFrequency::Fixed(v)),
+                _ => None,
+            }
+        }
+    }
+}
+
+impl GeneratorOptions {
+    /// Creates generator options with a uniform density.
+    ///
+    /// ## Arguments
+    /// - `category`  - The position of the combining glyphs.
+    /// - `frequency` - How many characters will have combining glyphs added to them.
+    /// - `stacking`  - The maximum number of combining glyphs on a single character.
+    pub fn new(category: GlyphPosition, frequency: GeneratorFrequency, stacking: usize) -> Self {
+        GeneratorOptions {
+            category,
+            frequency,
+            stacking,
+            density: Density::default(),
+        }
+    }
+
+    /// Parses generator options from a `POSITION:FREQUENCY:STACKING[:DENSITY]` string
+    /// (e.g. `above:60%:1` or `below:5:3:ramp-up`).
+    ///
+    /// ## Arguments
+    /// - `str`     - The string to parse.
+    /// - `density` - The density used if the string doesn't specify one.
+    pub fn parse(str: &str, density: &Density) -> Option<Self> {
+        let mut parts = str.splitn(4, ':');
+        let category = GlyphPosition::from_name(parts.next()?)?;
+        let frequency = GeneratorFrequency::parse(parts.next()?)?;
+        let stacking = parts.next()?.parse::<usize>().ok()?;
+        let density = match parts.next() {
+            None => density.clone(),
+            Some(density) => Density::parse(density)?,
+        };
+
+        Some(GeneratorOptions::new(category, frequency, stacking).density(density))
+    }
+
+    /// Sets how likely each position of the string is to be picked.
+    pub fn density(mut self, density: Density) -> Self {
+        self.density = density;
+        self
+    }
+}
+
+impl<'a> OptionsBuilder<'a> {
+    /// Allows combining glyphs to be added to characters they were not designed for.
+    pub fn allow_unreadable(mut self, allow: bool) -> Self {
+        self.options.allow_unreadable = allow;
+        self
+    }
+
+    /// Sets the maximum length of the processed string.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.options.limit = Some(limit);
+        self
+    }
+
+    /// Sets the unit used to measure the length of the processed string.
+    pub fn limit_unit(mut self, unit: LimitUnit) -> Self {
+        self.options.limit_unit = unit;
+        self
+    }
+
+    /// Sets the seed for the random number generator.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.options.seed = Some(seed);
+        self
+    }
+
+    /// Adds a generator for a category of combining glyphs.
+    ///
+    /// ## Arguments
+    /// - `category`  - The position of the combining glyphs.
+    /// - `frequency` - How many characters will have combining glyphs added to them.
+    /// - `stacking`  - The maximum number of combining glyphs on a single character.
+    pub fn generator(
+        mut self,
+        category: GlyphPosition,
+        frequency: GeneratorFrequency,
+        stacking: usize,
+    ) -> Self {
+        self.options
+            .generator
+            .push(GeneratorOptions::new(category, frequency, stacking));
+        self
+    }
+
+    /// Adds a generator for a category of combining glyphs.
+    ///
+    /// ## Arguments
+    /// - `generator` - The generator options.
+    pub fn generator_options(mut self, generator: GeneratorOptions) -> Self {
+        self.options.generator.push(generator);
+        self
+    }
+
+    /// Protects spans of text from having combining glyphs added.
+    ///
+    /// ## Arguments
+    /// - `protector` - The detector for protected spans.
+    pub fn protect(mut self, protector: Protector) -> Self {
+        self.options.protect.push(protector);
+        self
+    }
+
+    /// Sorts the combining glyphs of every character by their canonical combining class.
+    /// This keeps the glyphs from being rearranged when the output is normalized.
+    pub fn canonical_order(mut self, enabled: bool) -> Self {
+        self.options.canonical_order = enabled;
+        self
+    }
+
+    /// Sets the normalization form applied to the input and output.
+    pub fn normalize(mut self, normalization: Normalization) -> Self {
+        self.options.normalize = Some(normalization);
+        self
+    }
+
+    /// Creates the options.
+    pub fn build(self) -> Options<'a> {
+        self.options
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
 impl<'a> Joinerator<'a> {
+    /// Creates a new Joinerator.
+    ///
+    /// ## Arguments
+    /// - `options` - The options for generating combining glyphs.
     pub fn new(options: Options<'a>) -> Self {
+        let stable_only = options.canonical_order || options.normalize.is_some();
         let mut glyphs = HashMap::new();
         for category in options.generator.iter().map(|g| g.category) {
             glyphs.insert(
@@ -50,20 +272,29 @@ impl<'a> Joinerator<'a> {
                     .glyphs
                     .iter()
                     .filter(|g| g.position == category)
+                    .filter(|g| !stable_only || Normalization::Nfd.is_stable(g.codepoint))
                     .cloned()
                     .collect(),
             );
         }
 
+        let rng = match options.seed {
+            Some(seed) => ChaChaRng::seed_from_u64(seed),
+            None => ChaChaRng::from_entropy(),
+        };
+
         Joinerator {
             options,
             glyphs,
-            rng: thread_rng(),
+            rng,
         }
     }
 
     /// Processes a string, adding Unicode combining characters to it.
     ///
+    /// The string is segmented into extended grapheme clusters, and combining characters are only
+    /// ever added after the last code point of a cluster.
+    ///
     /// ## Arguments
     /// - `input - The input string.
     ///
@@ -72,21 +303,51 @@ impl<'a> Joinerator<'a> {
     /// The frequency and distribution of the combining characters is based on the options given
     /// to the Joinerator when it was initialized.
     pub fn process(&mut self, input: &str) -> String {
-        let input_len = input.chars().count();
+        match self.options.normalize {
+            Some(normalization) => {
+                let output = self.generate(&normalization.apply(input));
+                normalization.apply(&output)
+            }
+            None if self.options.canonical_order => {
+                normalize::canonical_order(&self.generate(input))
+            }
+            None => self.generate(input),
+        }
+    }
+
+    /// Adds Unicode combining characters to a string, without any normalization or reordering.
+    ///
+    /// ## Arguments
+    /// - `input - The input string.
+    fn generate(&mut self, input: &str) -> String {
+        let input_len = self.options.limit_unit.measure(input);
         let mut bucket = self.create_bucket(input);
-        let mut passes = self.create_passes(input);
-        let mut pass_buffer: Vec<bool> = Vec::with_capacity(input_len);
-        pass_buffer.resize(input_len, false);
+        let mut passes = self.create_passes(&bucket);
+        let mut pass_buffer: Vec<bool> = Vec::with_capacity(bucket.eligible.len());
+        pass_buffer.resize(bucket.eligible.len(), false);
+
+        // Added combining glyphs are free when measuring in grapheme clusters, so the limit only
+        // applies to the input string.
+        let limit = match self.options.limit {
+            Some(limit) if self.options.limit_unit == LimitUnit::Graphemes => {
+                if limit < input_len {
+                    return input.to_owned();
+                }
 
-        if self.options.limit.is_some() && self.options.limit.unwrap() <= input_len {
+                None
+            }
+            limit => limit,
+        };
+
+        if limit.is_some_and(|limit| limit <= input_len) {
             return input.to_owned();
         }
 
-        let (mut remaining, frequency_modifier) = if self.options.limit.is_some() {
-            let limit = self.options.limit.unwrap();
+        let (mut remaining, frequency_modifier) = if let Some(limit) = limit {
             (
                 limit - input_len,
-                ((limit - input_len) as f32) / (passes.total_additions as f32),
+                ((limit - input_len) as f32)
+                    / (passes.total_additions as f32 * self.average_glyph_length()),
             )
         } else {
             (usize::max_value(), 1.0)
@@ -94,7 +355,7 @@ impl<'a> Joinerator<'a> {
 
         // Execute passes.
         for _pass in 0..passes.total_iterations {
-            for (_category, pass_descriptor) in passes.descriptors.borrow_mut() {
+            for pass_descriptor in passes.descriptors.iter_mut() {
                 self.run_pass(
                     &mut bucket,
                     pass_descriptor,
@@ -106,45 +367,57 @@ impl<'a> Joinerator<'a> {
 
         // Apply passes.
         //  -> For every character
-        //    -> For every category (glyph position)
-        //       -> Add an applicable combining glyph.
+        //    -> For every generator
+        //       -> Add applicable combining glyphs until the limit is reached.
         bucket.chars.shuffle(&mut self.rng);
         for char in bucket.chars.iter_mut() {
-            char.combined.push(char.primary_glyph);
-
-            for (category, c) in char.additional_glyphs.iter() {
-                if c.get() < 1 {
-                    continue;
-                }
-
-                let count = if self.options.limit.is_some() {
-                    min(c.get(), remaining)
-                } else {
-                    c.get()
-                };
-
-                // Determine what combining glyphs can be applied to the primary glyph.
-                let category_glyphs = self.glyphs.get(category).unwrap();
-                let applicable_glyphs: Vec<&Glyph> = if self.options.allow_unreadable {
-                    category_glyphs.iter().collect()
-                } else {
-                    category_glyphs
-                        .iter()
-                        .filter(|g| g.is_applicable(char.primary_glyph))
-                        .collect()
-                };
-
-                if applicable_glyphs.len() < 1 {
-                    continue;
-                }
+            char.combined.push_str(&char.cluster);
+
+            for (descriptor, c) in passes.descriptors.iter().zip(char.additional_glyphs.iter()) {
+                let added = self.add_glyphs(
+                    &mut char.combined,
+                    char.primary_glyph,
+                    descriptor.category,
+                    c.get(),
+                    &mut remaining,
+                );
+                c.replace(added);
+            }
+        }
 
-                // Add the combining glyphs to the primary glyph.
-                for _ in 0..count {
-                    char.combined
-                        .push(applicable_glyphs.choose(&mut self.rng).unwrap().codepoint)
+        // Fill the remaining length.
+        //  -> While there is space left
+        //    -> For every character
+        //      -> For every generator that isn't fully stacked (and can be picked)
+        //         -> Add one more applicable combining glyph.
+        if limit.is_some() {
+            let mut changed = true;
+            while changed && remaining > 0 {
+                changed = false;
+                for char in bucket.chars.iter_mut() {
+                    for (descriptor, c) in
+                        passes.descriptors.iter().zip(char.additional_glyphs.iter())
+                    {
+                        if char.protected
+                            || c.get() >= descriptor.stacking
+                            || !descriptor.can_pick(char.position)
+                        {
+                            continue;
+                        }
+
+                        let added = self.add_glyphs(
+                            &mut char.combined,
+                            char.primary_glyph,
+                            descriptor.category,
+                            1,
+                            &mut remaining,
+                        );
+                        if added > 0 {
+                            c.replace(c.get() + added);
+                            changed = true;
+                        }
+                    }
                 }
-
-                remaining -= count;
             }
         }
 
@@ -153,20 +426,114 @@ impl<'a> Joinerator<'a> {
         bucket.chars.into_iter().map(|c| c.combined).collect()
     }
 
-    /// Creates the bucket used to hold information about each character in the input string.
+    /// Adds random combining glyphs of a specific category to a string.
+    ///
+    /// ## Arguments
+    /// - `combined`  - The string to add the combining glyphs to.
+    /// - `primary`   - The primary glyph that the combining glyphs will be combined with.
+    /// - `category`  - The category of combining glyphs.
+    /// - `count`     - The number of combining glyphs to add.
+    /// - `remaining` - The remaining length before the limit is reached.
+    ///
+    /// ## Returns
+    /// The number of combining glyphs that were added.
+    fn add_glyphs(
+        &mut self,
+        combined: &mut String,
+        primary: char,
+        category: GlyphPosition,
+        count: usize,
+        remaining: &mut usize,
+    ) -> usize {
+        if count < 1 {
+            return 0;
+        }
+
+        // Determine what combining glyphs can be applied to the primary glyph.
+        let category_glyphs = self.glyphs.get(&category).unwrap();
+        let applicable_glyphs: Vec<&Glyph> = category_glyphs
+            .iter()
+            .filter(|g| g.weight > 0.0)
+            .filter(|g| self.options.allow_unreadable || g.is_applicable(primary))
+            .collect();
+
+        if applicable_glyphs.is_empty() {
+            return 0;
+        }
+
+        // Weights that can't be used (e.g. infinite weights) fall back to a uniform choice.
+        let weighted = applicable_glyphs
+            .iter()
+            .map(|g| g.weight)
+            .sum::<f32>()
+            .is_finite();
+
+        // Add the combining glyphs to the primary glyph.
+        let mut added = 0;
+        for _ in 0..count {
+            let rng = &mut self.rng;
+            let chosen = if weighted {
+                applicable_glyphs.choose_weighted(rng, |g| g.weight).ok()
+            } else {
+                None
+            };
+
+            let glyph = chosen
+                .or_else(|| applicable_glyphs.choose(rng))
+                .unwrap()
+                .codepoint;
+            let length = self.options.limit_unit.measure_glyph(glyph);
+            if length > *remaining {
+                break;
+            }
+
+            combined.push(glyph);
+            *remaining -= length;
+            added += 1;
+        }
+
+        added
+    }
+
+    /// Calculates the average length of the combining glyphs that can be generated.
+    /// This is measured in the unit used for limiting.
+    fn average_glyph_length(&self) -> f32 {
+        let unit = self.options.limit_unit;
+        let lengths: Vec<usize> = self
+            .glyphs
+            .values()
+            .flat_map(|v| v.iter())
+            .map(|g| unit.measure_glyph(g.codepoint))
+            .collect();
+
+        if lengths.is_empty() {
+            1.0
+        } else {
+            (lengths.iter().sum::<usize>() as f32) / (lengths.len() as f32)
+        }
+    }
+
+    /// Creates the bucket used to hold information about each grapheme cluster in the input string.
+    /// Clusters that overlap a protected span are marked as protected and will not be eligible for
+    /// combining glyphs.
     ///
     /// ## Arguments
     /// - `input` - The input string.
     ///
     /// ## Returns
-    /// A struct containing a vector which holds data related to each character in the input string.
+    /// A struct containing a vector which holds data related to each grapheme cluster in the input
+    /// string.
     fn create_bucket(&self, input: &str) -> Bucket {
         let mut bucket = Bucket::new();
+        let spans = protect::find_spans(&self.options.protect, input);
+
+        for (index, (offset, cluster)) in input.grapheme_indices(true).enumerate() {
+            let mut item = BucketItem::new(index, cluster);
+            item.additional_glyphs = vec![Cell::new(0); self.options.generator.len()];
 
-        for (index, char) in input.chars().enumerate() {
-            let mut item = BucketItem::new(index, char);
-            for category in self.options.generator.iter().map(|g| g.category) {
-                item.additional_glyphs.insert(category, Cell::new(0));
+            item.protected = protect::is_protected(&spans, &(offset..offset + cluster.len()));
+            if !item.protected {
+                bucket.eligible.push(index);
             }
 
             bucket.chars.push(item);
@@ -176,31 +543,42 @@ impl<'a> Joinerator<'a> {
     }
 
     /// Creates and calculates information about each of the passes to be run against the input string.
+    /// There is one pass descriptor for every generator, ordered by glyph position. Generators with
+    /// the same glyph position keep the order they were added in.
     ///
     /// ## Arguments
-    /// - `input` - The input string.
+    /// - `bucket` - The bucket created by `create_bucket`.
     ///
     /// ## Returns
     /// A struct containing information and metadata about the generator passes that will be run.
-    fn create_passes(&self, input: &str) -> Passes {
-        let mut descriptors: HashMap<GlyphPosition, PassDescriptor> = HashMap::new();
+    fn create_passes(&self, bucket: &Bucket) -> Passes {
+        let mut descriptors: Vec<PassDescriptor> = Vec::with_capacity(self.options.generator.len());
         let mut additions: usize = 0;
+        let eligible_len = bucket.eligible.len();
+
+        let mut generators: Vec<&GeneratorOptions> = self.options.generator.iter().collect();
+        generators.sort_by_key(|g| g.category);
 
-        for generator in self.options.generator.iter() {
+        for generator in generators {
             let chars = match generator.frequency {
                 GeneratorFrequency::Fixed(n) => n,
-                GeneratorFrequency::Percentage(p) => (p * (input.chars().count() as f32)) as usize,
+                GeneratorFrequency::Percentage(p) => (p * (eligible_len as f32)) as usize,
+            };
+
+            let weights = match generator.density {
+                Density::Uniform => None,
+                ref density => Some(density.weights(bucket.chars.len())),
             };
 
             additions += chars * generator.stacking;
-            descriptors.insert(
-                generator.category,
-                PassDescriptor {
-                    category: generator.category,
-                    passes: generator.stacking,
-                    chars,
-                },
-            );
+            descriptors.push(PassDescriptor {
+                index: descriptors.len(),
+                category: generator.category,
+                passes: generator.stacking,
+                stacking: generator.stacking,
+                chars,
+                weights,
+            });
         }
 
         Passes {
@@ -212,17 +590,17 @@ impl<'a> Joinerator<'a> {
                 .iter()
                 .map(|v| v.stacking)
                 .max()
-                .unwrap(),
+                .unwrap_or(0),
         }
     }
 
-    /// Executes a pass of the generator for a specific category.
-    /// This should be done for each category.
+    /// Executes a pass of a generator.
+    /// This should be done for each generator.
     ///
     /// ## Arguments
     /// - `bucket`     - The bucket created by `create_bucket`
     /// - `descriptor` - A pass descriptor created by `create_passes`
-    /// - `buffer`     - A vector of booleans the size of the input string.
+    /// - `buffer`     - A vector of booleans the size of the eligible positions in the bucket.
     ///                  This is intended to be reused.
     /// - `modifier`   - The frequency modifier (used for limiting)
     fn run_pass(
@@ -240,20 +618,43 @@ impl<'a> Joinerator<'a> {
 
         // Fill the buffer.
         let bound = ((descriptor.chars as f32) * modifier) as usize;
-        for i in 0..buffer.len() {
-            buffer[i] = if i < bound { true } else { false }
-        }
+        match descriptor.weights {
+            None => {
+                for i in 0..buffer.len() {
+                    buffer[i] = if i < bound { true } else { false }
+                }
+
+                // Shuffle the buffer.
+                buffer.shuffle(&mut self.rng);
+            }
 
-        // Shuffle the buffer.
-        buffer.shuffle(&mut self.rng);
+            Some(ref weights) => {
+                // Pick weighted positions without replacement.
+                // Each position gets a random key of `ln(u) / weight`, and the largest keys win.
+                let mut keys: Vec<(f64, usize)> = bucket
+                    .eligible
+                    .iter()
+                    .map(|position| weights[*position])
+                    .enumerate()
+                    .filter(|(_, w)| *w > 0.0)
+                    .map(|(i, w)| (self.rng.gen::<f64>().ln() / (w as f64), i))
+                    .collect();
+
+                keys.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+                for v in buffer.iter_mut() {
+                    *v = false;
+                }
+
+                for (_, i) in keys.into_iter().take(bound) {
+                    buffer[i] = true;
+                }
+            }
+        }
 
         // Update the bucket items with the results of the shuffle.
-        for char in bucket.chars.iter_mut() {
-            if buffer[char.position] {
-                let num = char
-                    .additional_glyphs
-                    .get_mut(&descriptor.category)
-                    .unwrap();
+        for (i, position) in bucket.eligible.iter().enumerate() {
+            if buffer[i] {
+                let num = &bucket.chars[*position].additional_glyphs[descriptor.index];
                 num.replace(num.get() + 1);
             }
         }
@@ -264,40 +665,256 @@ impl<'a> Joinerator<'a> {
 
 struct Bucket {
     chars: Vec<BucketItem>,
+    eligible: Vec<usize>,
 }
 
 struct BucketItem {
-    additional_glyphs: HashMap<GlyphPosition, Cell<usize>>,
+    additional_glyphs: Vec<Cell<usize>>,
     primary_glyph: char,
+    cluster: String,
     combined: String,
     position: usize,
+    protected: bool,
 }
 
 struct Passes {
     total_iterations: usize,
     total_additions: usize,
-    descriptors: HashMap<GlyphPosition, PassDescriptor>,
+    descriptors: Vec<PassDescriptor>,
 }
 
 struct PassDescriptor {
+    index: usize,
     category: GlyphPosition,
     passes: usize,
+    stacking: usize,
     chars: usize,
+    weights: Option<Vec<f32>>,
 }
 
 impl Bucket {
     pub fn new() -> Self {
-        Bucket { chars: Vec::new() }
+        Bucket {
+            chars: Vec::new(),
+            eligible: Vec::new(),
+        }
+    }
+}
+
+impl PassDescriptor {
+    /// Checks if a position can be picked by the generator's density.
+    pub fn can_pick(&self, position: usize) -> bool {
+        match self.weights {
+            None => true,
+            Some(ref weights) => weights[position] > 0.0,
+        }
     }
 }
 
 impl BucketItem {
-    pub fn new(index: usize, glyph: char) -> Self {
+    /// Creates a bucket item for a grapheme cluster.
+    /// The first code point of the cluster is used as the primary glyph.
+    pub fn new(index: usize, cluster: &str) -> Self {
         BucketItem {
-            additional_glyphs: HashMap::new(),
-            primary_glyph: glyph,
+            additional_glyphs: Vec::new(),
+            primary_glyph: cluster.chars().next().unwrap(),
+            cluster: cluster.to_owned(),
             position: index,
             combined: String::new(),
+            protected: false,
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repertoire;
+
+    fn builder() -> OptionsBuilder<'static> {
+        Options::builder(repertoire::get("default").unwrap())
+            .generator(GlyphPosition::ABOVE, GeneratorFrequency::Percentage(0.6), 2)
+            .generator(GlyphPosition::BELOW, GeneratorFrequency::Percentage(0.6), 2)
+    }
+
+    #[test]
+    fn same_seed_gives_same_output() {
+        let input = "The quick brown fox jumps over the lazy dog.";
+        let first = Joinerator::new(builder().seed(42).build()).process(input);
+        let second = Joinerator::new(builder().seed(42).build()).process(input);
+        let other = Joinerator::new(builder().seed(43).build()).process(input);
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn seeded_output_is_stable() {
+        // If this changes, the output for a seed is no longer the same across versions.
+        let output = Joinerator::new(builder().seed(42).build()).process("Hello, world!");
+        assert_eq!(
+            output,
+            "H\u{302}\u{33c}\u{33b}e\u{34a}\u{33d}l\u{31e}l\u{358}\u{35a}o\u{351}\u{339}\u{339}, w\u{302}\u{316}o\u{364}\u{330}r\u{310}\u{32c}l\u{311}d\u{36b}!"
+        );
+    }
+
+    #[test]
+    fn same_seed_continues_the_sequence() {
+        let mut joinerator = Joinerator::new(builder().seed(42).build());
+        let first = joinerator.process("hello");
+        let second = joinerator.process("hello");
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn marks_are_added_after_grapheme_clusters() {
+        let input = "cafe\u{0301} \u{1F469}\u{200D}\u{1F4BB} \u{1F1FA}\u{1F1F8}!";
+        let options = Options::builder(repertoire::get("default").unwrap())
+            .generator(GlyphPosition::ABOVE, GeneratorFrequency::Percentage(1.0), 3)
+            .allow_unreadable(true)
+            .seed(1)
+            .build();
+
+        let output = Joinerator::new(options).process(input);
+        let clusters: Vec<&str> = output.graphemes(true).collect();
+        assert_eq!(clusters.len(), input.graphemes(true).count());
+        for (cluster, original) in clusters.iter().zip(input.graphemes(true)) {
+            assert!(
+                cluster.starts_with(original),
+                "{:?} {:?}",
+                cluster,
+                original
+            );
+        }
+    }
+
+    #[test]
+    fn weights() {
+        let combines = regex::Regex::new(".").unwrap();
+        let repertoire = Repertoire::new(
+            "test".to_owned(),
+            "Test".to_owned(),
+            vec![
+                Glyph::new('\u{0300}', GlyphPosition::ABOVE, combines.clone()).with_weight(0.0),
+                Glyph::new('\u{0301}', GlyphPosition::ABOVE, combines),
+            ],
+        );
+
+        let options = Options::builder(&repertoire)
+            .generator(GlyphPosition::ABOVE, GeneratorFrequency::Percentage(1.0), 3)
+            .seed(5)
+            .build();
+
+        let output = Joinerator::new(options).process("abcdef");
+        assert!(output.contains('\u{0301}'));
+        assert!(!output.contains('\u{0300}'));
+    }
+
+    #[test]
+    fn marks_are_added_to_stylized_letters() {
+        let combines = regex::Regex::new("[a-z]").unwrap();
+        let repertoire = Repertoire::new(
+            "test".to_owned(),
+            "Test".to_owned(),
+            vec![
+                Glyph::new('\u{0300}', GlyphPosition::ABOVE, combines.clone()),
+                Glyph::new('\u{0301}', GlyphPosition::ABOVE, combines),
+            ],
+        );
+
+        let options = Options::builder(&repertoire)
+            .generator(GlyphPosition::ABOVE, GeneratorFrequency::Percentage(1.0), 2)
+            .seed(3)
+            .build();
+
+        let mut bold = crate::transform::get("bold", None).unwrap();
+        let input = bold.transform("hello".to_owned()).unwrap();
+        let output = Joinerator::new(options).process(&input);
+
+        let clusters: Vec<&str> = output.graphemes(true).collect();
+        assert_eq!(clusters.len(), 5);
+        for (cluster, letter) in clusters.iter().zip(input.chars()) {
+            assert!(cluster.starts_with(letter));
+            assert_eq!(cluster.chars().count(), 3, "{:?}", cluster);
+        }
+    }
+
+    #[test]
+    fn unusable_weights_fall_back_to_uniform() {
+        let combines = regex::Regex::new(".").unwrap();
+        let mut glyphs = vec![
+            Glyph::new('\u{0300}', GlyphPosition::ABOVE, combines.clone()),
+            Glyph::new('\u{0301}', GlyphPosition::ABOVE, combines),
+        ];
+        glyphs[0].weight = std::f32::INFINITY;
+        glyphs[1].weight = std::f32::MAX;
+
+        let repertoire = Repertoire::new("test".to_owned(), "Test".to_owned(), glyphs);
+        let options = Options::builder(&repertoire)
+            .generator(GlyphPosition::ABOVE, GeneratorFrequency::Percentage(1.0), 3)
+            .seed(5)
+            .build();
+
+        let output = Joinerator::new(options).process("abcdef");
+        assert_eq!(output.chars().count(), 6 * 4);
+    }
+
+    #[test]
+    fn generator_frequency_parse() {
+        match GeneratorFrequency::parse("60%") {
+            Some(GeneratorFrequency::Percentage(p)) => assert!((p - 0.6).abs() < 1e-6),
+            _ => panic!("expected a percentage"),
         }
+
+        match GeneratorFrequency::parse("5") {
+            Some(GeneratorFrequency::Fixed(5)) => {}
+            _ => panic!("expected a fixed number"),
+        }
+
+        for invalid in &["0%", "101%", "0", "-1", "x%", ""] {
+            assert!(GeneratorFrequency::parse(invalid).is_none(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn generator_options_parse() {
+        let generator = GeneratorOptions::parse("below:5:3", &Density::RampUp).unwrap();
+        assert_eq!(generator.category, GlyphPosition::BELOW);
+        assert_eq!(generator.stacking, 3);
+        assert_eq!(generator.density, Density::RampUp);
+
+        let generator =
+            GeneratorOptions::parse("Above-Right:10%:1:0,1", &Density::Uniform).unwrap();
+        assert_eq!(generator.category, GlyphPosition::ABOVE_RIGHT);
+        assert_eq!(generator.density, Density::Points(vec![0.0, 1.0]));
+
+        for invalid in &[
+            "above",
+            "above:10%",
+            "sideways:10%:1",
+            "above:10%:x",
+            "above:10%:1:x",
+        ] {
+            assert!(
+                GeneratorOptions::parse(invalid, &Density::Uniform).is_none(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn several_generators_for_one_position() {
+        let options = Options::builder(repertoire::get("default").unwrap())
+            .generator(GlyphPosition::ABOVE, GeneratorFrequency::Fixed(2), 1)
+            .generator(GlyphPosition::ABOVE, GeneratorFrequency::Fixed(2), 1)
+            .seed(9)
+            .build();
+
+        let output = Joinerator::new(options).process("abcdefgh");
+        assert_eq!(output.chars().count(), "abcdefgh".len() + 4);
     }
 }
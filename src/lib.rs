@@ -0,0 +1,106 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+//! A library for stylizing text with Unicode combining marks.
+//!
+//! The main entry point is [`Joinerator`], which is created from [`Options`] (see
+//! [`Options::builder`]) and a [`Repertoire`] of combining glyphs. The built-in repertoires
+//! can be found with [`repertoire::builtin`] and [`repertoire::get`].
+//!
+//! ```
+//! use joinerator::{repertoire, GeneratorFrequency, GlyphPosition, Joinerator, Options};
+//!
+//! let options = Options::builder(repertoire::get("default").unwrap())
+//!     .generator(GlyphPosition::ABOVE, GeneratorFrequency::Percentage(0.6), 1)
+//!     .seed(1)
+//!     .build();
+//!
+//! let mut joinerator = Joinerator::new(options);
+//! let output = joinerator.process("Hello, world!");
+//! assert!(output.chars().count() > "Hello, world!".len());
+//! ```
+//!
+//! Combining marks can be removed again with [`strip`].
+//!
+//! Settings can be loaded from configuration files and named presets with [`Config`].
+//!
+//! Text can be transformed before processing with a [`Transformer`] from the [`transform`]
+//! module, and read or written through the [`content::Provider`] and [`content::Consumer`]
+//! traits.
+//!
+//! A [`service::Service`] handles requests with one loaded set of repertoires, and can be
+//! served over HTTP with [`server::Server`] (requires the `server` feature) or over a Unix domain
+//! socket with [`daemon::Daemon`] (requires the `daemon` feature). Settings can be tuned with a
+//! live preview in [`interactive::Interactive`] (requires the `interactive` feature), or changed
+//! between lines in a [`repl::Repl`].
+// -------------------------------------------------------------------------------------------------
+extern crate failure;
+extern crate rand;
+extern crate rand_chacha;
+extern crate regex;
+extern crate serde;
+extern crate serde_cbor;
+extern crate serde_regex;
+extern crate serde_yaml;
+extern crate toml;
+extern crate unicode_normalization;
+extern crate unicode_segmentation;
+
+#[macro_use]
+extern crate lazy_static;
+
+#[cfg(feature = "clipboard_support")]
+extern crate clipboard;
+
+#[cfg(any(feature = "server", feature = "daemon"))]
+extern crate serde_json;
+
+#[cfg(feature = "server")]
+extern crate tiny_http;
+
+#[cfg(feature = "interactive")]
+extern crate crossterm;
+
+#[cfg(feature = "repl")]
+extern crate rustyline;
+
+#[cfg(target_os = "linux")]
+extern crate inotify;
+
+#[cfg(target_os = "linux")]
+extern crate libc;
+
+// -------------------------------------------------------------------------------------------------
+pub mod config;
+pub mod content;
+#[cfg(all(unix, feature = "daemon"))]
+pub mod daemon;
+pub mod density;
+#[cfg(feature = "interactive")]
+pub mod interactive;
+pub mod joinerator;
+pub mod limit;
+pub mod markup;
+pub mod normalize;
+pub mod protect;
+pub mod repertoire;
+pub mod repl;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod service;
+pub mod strip;
+pub mod transform;
+
+// -------------------------------------------------------------------------------------------------
+pub use crate::config::{Config, Settings};
+pub use crate::density::Density;
+pub use crate::joinerator::{
+    GeneratorFrequency, GeneratorOptions, Joinerator, Options, OptionsBuilder,
+};
+pub use crate::limit::LimitUnit;
+pub use crate::markup::Format;
+pub use crate::normalize::Normalization;
+pub use crate::protect::Protector;
+pub use crate::repertoire::{Glyph, GlyphPosition, Repertoire, RepertoireSet};
+pub use crate::strip::{strip, StripMode, StripOptions, Stripped};
+pub use crate::transform::Transformer;
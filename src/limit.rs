@@ -0,0 +1,183 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use unicode_segmentation::UnicodeSegmentation;
+// -------------------------------------------------------------------------------------------------
+
+/// The unit used to measure string length when enforcing a limit.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum LimitUnit {
+    /// Unicode scalar values (Rust `char`s).
+    #[default]
+    Chars,
+
+    /// Extended grapheme clusters.
+    /// Added combining glyphs are not counted, since they never form a new cluster. Their number
+    /// is only limited by the stacking of each generator.
+    Graphemes,
+
+    /// UTF-8 encoded bytes.
+    Utf8Bytes,
+
+    /// UTF-16 code units.
+    Utf16Units,
+}
+
+/// A named length limit for a specific platform.
+pub struct LimitPreset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub limit: usize,
+    pub unit: LimitUnit,
+}
+
+/// The names of every limit unit that can be parsed with `LimitUnit::from_name`.
+pub const UNIT_NAMES: &[&str] = &["chars", "graphemes", "bytes", "utf16"];
+
+/// The built-in limit presets.
+pub const PRESETS: &[LimitPreset] = &[
+    LimitPreset {
+        name: "discord",
+        description: "Discord message",
+        limit: 2000,
+        unit: LimitUnit::Utf16Units,
+    },
+    LimitPreset {
+        name: "irc",
+        description: "IRC message (leaving room for the command prefix)",
+        limit: 400,
+        unit: LimitUnit::Utf8Bytes,
+    },
+    LimitPreset {
+        name: "sms",
+        description: "Single UCS-2 SMS segment",
+        limit: 70,
+        unit: LimitUnit::Utf16Units,
+    },
+    LimitPreset {
+        name: "twitter",
+        description: "Twitter post",
+        limit: 280,
+        unit: LimitUnit::Utf16Units,
+    },
+];
+
+// -------------------------------------------------------------------------------------------------
+
+impl LimitUnit {
+    /// Gets a limit unit from its name (see `UNIT_NAMES`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "chars" => Some(LimitUnit::Chars),
+            "graphemes" => Some(LimitUnit::Graphemes),
+            "bytes" => Some(LimitUnit::Utf8Bytes),
+            "utf16" => Some(LimitUnit::Utf16Units),
+            _ => None,
+        }
+    }
+
+    /// Measures the length of a string.
+    ///
+    /// ## Arguments
+    /// - `str` - The string to measure.
+    pub fn measure(self, str: &str) -> usize {
+        match self {
+            LimitUnit::Chars => str.chars().count(),
+            LimitUnit::Graphemes => str.graphemes(true).count(),
+            LimitUnit::Utf8Bytes => str.len(),
+            LimitUnit::Utf16Units => str.encode_utf16().count(),
+        }
+    }
+
+    /// Measures the length of a combining glyph added to a string.
+    ///
+    /// ## Arguments
+    /// - `glyph` - The combining glyph to measure.
+    pub fn measure_glyph(self, glyph: char) -> usize {
+        match self {
+            LimitUnit::Chars => 1,
+            LimitUnit::Graphemes => 0,
+            LimitUnit::Utf8Bytes => glyph.len_utf8(),
+            LimitUnit::Utf16Units => glyph.len_utf16(),
+        }
+    }
+}
+
+/// Gets a built-in limit preset by its name.
+///
+/// ## Arguments
+/// - `name` - The preset name.
+pub fn preset(name: &str) -> Option<&'static LimitPreset> {
+    PRESETS.iter().find(|p| p.name == name)
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::joinerator::{GeneratorFrequency, Joinerator, Options};
+    use crate::repertoire::{self, GlyphPosition};
+
+    const INPUT: &str = "hello, world";
+
+    fn process(limit: usize, unit: LimitUnit) -> String {
+        let options = Options::builder(repertoire::get("default").unwrap())
+            .generator(GlyphPosition::ABOVE, GeneratorFrequency::Percentage(1.0), 4)
+            .generator(GlyphPosition::BELOW, GeneratorFrequency::Percentage(1.0), 4)
+            .limit(limit)
+            .limit_unit(unit)
+            .seed(1)
+            .build();
+
+        Joinerator::new(options).process(INPUT)
+    }
+
+    #[test]
+    fn from_name_parses_every_unit_name() {
+        for name in UNIT_NAMES {
+            assert!(LimitUnit::from_name(name).is_some(), "{}", name);
+        }
+
+        assert_eq!(LimitUnit::from_name("words"), None);
+        assert_eq!(LimitUnit::default(), LimitUnit::Chars);
+    }
+
+    #[test]
+    fn measure() {
+        let str = "e\u{0301}\u{1F600}";
+        assert_eq!(LimitUnit::Chars.measure(str), 3);
+        assert_eq!(LimitUnit::Graphemes.measure(str), 2);
+        assert_eq!(LimitUnit::Utf8Bytes.measure(str), 7);
+        assert_eq!(LimitUnit::Utf16Units.measure(str), 4);
+    }
+
+    #[test]
+    fn limit_chars() {
+        assert_eq!(LimitUnit::Chars.measure(&process(20, LimitUnit::Chars)), 20);
+    }
+
+    #[test]
+    fn limit_bytes() {
+        let output = process(40, LimitUnit::Utf8Bytes);
+        let length = LimitUnit::Utf8Bytes.measure(&output);
+        assert!(length <= 40 && length > 40 - 4, "{}", length);
+    }
+
+    #[test]
+    fn limit_utf16() {
+        assert_eq!(
+            LimitUnit::Utf16Units.measure(&process(30, LimitUnit::Utf16Units)),
+            30
+        );
+    }
+
+    #[test]
+    fn limit_graphemes_only_applies_to_input() {
+        let output = process(INPUT.len(), LimitUnit::Graphemes);
+        assert_eq!(LimitUnit::Graphemes.measure(&output), INPUT.len());
+        assert!(output.chars().count() > INPUT.len());
+
+        assert_eq!(process(INPUT.len() - 1, LimitUnit::Graphemes), INPUT);
+    }
+}
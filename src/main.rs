@@ -3,12 +3,11 @@
 // -------------------------------------------------------------------------------------------------
 extern crate ansi_term;
 extern crate clap;
-extern crate failure;
-extern crate rand;
+extern crate joinerator;
 extern crate regex;
-extern crate serde;
-extern crate serde_cbor;
-extern crate serde_regex;
+
+#[macro_use]
+extern crate failure;
 
 #[macro_use]
 extern crate lazy_static;
@@ -16,33 +15,49 @@ extern crate lazy_static;
 #[cfg(not(target = "windows"))]
 extern crate atty;
 
-#[cfg(feature = "clipboard_support")]
-extern crate clipboard;
-
-// -------------------------------------------------------------------------------------------------
-mod content;
-mod joinerator;
-mod repertoire;
-mod transform;
-
 // -------------------------------------------------------------------------------------------------
 use std::collections::linked_list::LinkedList;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fs::File;
+#[cfg(all(unix, feature = "daemon"))]
+use std::path::PathBuf;
 use std::process::exit;
-use std::thread::sleep;
 use std::time::Duration;
 
 use ansi_term::{Color, Style};
-use clap::{App, Arg, ArgMatches};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use failure::{Error, Fail};
+use regex::Regex;
 
 #[cfg(not(target = "windows"))]
 use atty::Stream;
 
-use crate::content::{Consumer, Provider};
-use crate::joinerator::{GeneratorFrequency, GeneratorOptions, Joinerator, Options};
-use crate::repertoire::{GlyphPosition, Repertoire};
-use crate::transform::Transformer;
+use joinerator::config::{Config, GeneratorSettings, Settings};
+use joinerator::content::file::WriteMode;
+use joinerator::content::streams::RecordDelimiter;
+use joinerator::content::tee::{ErrorPolicy, TeeConsumer};
+use joinerator::content::{self, Consumer, Provider};
+#[cfg(all(unix, feature = "daemon"))]
+use joinerator::daemon::{self, Daemon};
+#[cfg(feature = "interactive")]
+use joinerator::interactive::Interactive;
+use joinerator::limit;
+use joinerator::markup;
+use joinerator::normalize;
+use joinerator::protect;
+use joinerator::repertoire::{self, Repertoire, RepertoireSet};
+use joinerator::repl::Repl;
+#[cfg(feature = "server")]
+use joinerator::server::{self, Server};
+#[cfg(any(
+    feature = "server",
+    feature = "interactive",
+    all(unix, feature = "daemon")
+))]
+use joinerator::service::Service;
+use joinerator::transform;
+use joinerator::{Density, GeneratorFrequency, GeneratorOptions, GlyphPosition, Joinerator};
+use joinerator::{StripMode, StripOptions};
 // -------------------------------------------------------------------------------------------------
 
 struct Colors {
@@ -87,6 +102,12 @@ impl Colors {
 
 // -------------------------------------------------------------------------------------------------
 
+/// The longest time `main_loop` waits for a change before checking again.
+const WATCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The prefix of an `--input` or `--output` that is a file path.
+const FILE_PREFIX: &str = "file:";
+
 #[cfg(target = "windows")]
 const EOL: &str = "\r\n";
 
@@ -94,17 +115,6 @@ const EOL: &str = "\r\n";
 const EOL: &str = "\n";
 
 lazy_static! {
-    static ref REPERTOIRES: HashMap<String, Repertoire> = {
-        #[cfg(cached)]
-        {
-            serde_cbor::from_slice(include_bytes!("repertoire.cache")).unwrap()
-        }
-
-        #[cfg(not(cached))]
-        {
-            panic!("Project must be built with cached repertoires.")
-        }
-    };
     static ref COLORS: Colors = {
         #[cfg(target_os = "windows")]
         let enabled = ansi_term::enable_ansi_support().is_ok();
@@ -122,64 +132,219 @@ lazy_static! {
 
 // -------------------------------------------------------------------------------------------------
 
+/// The command line arguments for a generator.
+struct GeneratorArgs {
+    position: GlyphPosition,
+    stacking: &'static str,
+    stacking_alias: &'static str,
+    frequency: &'static str,
+    frequency_alias: &'static str,
+    short: Option<(&'static str, &'static str)>,
+    default: Option<(&'static str, &'static str)>,
+    description: &'static str,
+}
+
+/// The command line arguments for every generator.
+/// Generators without defaults are only used when one of their arguments is given.
+const GENERATOR_ARGS: &[GeneratorArgs] = &[
+    GeneratorArgs {
+        position: GlyphPosition::ABOVE,
+        stacking: "above:stacking",
+        stacking_alias: "above-stacking",
+        frequency: "above:frequency",
+        frequency_alias: "above-frequency",
+        short: Some(("a", "A")),
+        default: Some(("1", "60%")),
+        description: "go above",
+    },
+    GeneratorArgs {
+        position: GlyphPosition::BELOW,
+        stacking: "below:stacking",
+        stacking_alias: "below-stacking",
+        frequency: "below:frequency",
+        frequency_alias: "below-frequency",
+        short: Some(("b", "B")),
+        default: Some(("1", "60%")),
+        description: "go below",
+    },
+    GeneratorArgs {
+        position: GlyphPosition::THROUGH,
+        stacking: "through:stacking",
+        stacking_alias: "through-stacking",
+        frequency: "through:frequency",
+        frequency_alias: "through-frequency",
+        short: Some(("c", "C")),
+        default: Some(("0", "10%")),
+        description: "go through",
+    },
+    GeneratorArgs {
+        position: GlyphPosition::DOUBLE,
+        stacking: "double:stacking",
+        stacking_alias: "double-stacking",
+        frequency: "double:frequency",
+        frequency_alias: "double-frequency",
+        short: None,
+        default: None,
+        description: "join",
+    },
+    GeneratorArgs {
+        position: GlyphPosition::ENCLOSING,
+        stacking: "enclosing:stacking",
+        stacking_alias: "enclosing-stacking",
+        frequency: "enclosing:frequency",
+        frequency_alias: "enclosing-frequency",
+        short: None,
+        default: None,
+        description: "enclose",
+    },
+    GeneratorArgs {
+        position: GlyphPosition::OVERLAY,
+        stacking: "overlay:stacking",
+        stacking_alias: "overlay-stacking",
+        frequency: "overlay:frequency",
+        frequency_alias: "overlay-frequency",
+        short: None,
+        default: None,
+        description: "be drawn over",
+    },
+    GeneratorArgs {
+        position: GlyphPosition::ABOVE_RIGHT,
+        stacking: "above-right:stacking",
+        stacking_alias: "above-right-stacking",
+        frequency: "above-right:frequency",
+        frequency_alias: "above-right-frequency",
+        short: None,
+        default: None,
+        description: "attach to the top right of",
+    },
+    GeneratorArgs {
+        position: GlyphPosition::VARIATION,
+        stacking: "variation:stacking",
+        stacking_alias: "variation-stacking",
+        frequency: "variation:frequency",
+        frequency_alias: "variation-frequency",
+        short: None,
+        default: None,
+        description: "change the presentation of",
+    },
+];
+
+// -------------------------------------------------------------------------------------------------
+
 fn main() {
     let matches = handle_cli();
 
     // Handle action flags.
     if matches.is_present("list-repertoires") {
-        list_repertoires();
+        list_repertoires(&unwrap_or_exit(get_repertoires(&matches)));
         return;
     }
 
-    // Handle verbosity.
-    let verbose = if matches.is_present("verbose") {
-        true
-    } else if matches.is_present("quiet") {
-        false
-    } else {
-        match matches.value_of("output") {
-            Some("stdout") => false,
-            _ => true,
+    // Handle subcommands.
+    if let Some(strip_matches) = matches.subcommand_matches("strip") {
+        main_strip(strip_matches);
+        return;
+    }
+
+    #[cfg(feature = "server")]
+    {
+        if let Some(serve_matches) = matches.subcommand_matches("serve") {
+            main_serve(&matches, serve_matches);
+            return;
         }
-    };
+    }
+
+    if let Some(repl_matches) = matches.subcommand_matches("repl") {
+        main_repl(&matches, repl_matches);
+        return;
+    }
+
+    #[cfg(feature = "interactive")]
+    {
+        if let Some(interactive_matches) = matches.subcommand_matches("interactive") {
+            main_interactive(&matches, interactive_matches);
+            return;
+        }
+    }
+
+    #[cfg(all(unix, feature = "daemon"))]
+    {
+        if let Some(daemon_matches) = matches.subcommand_matches("daemon") {
+            main_daemon(&matches, daemon_matches);
+            return;
+        }
+    }
 
     // Initialize program.
-    let mut provider = get_provider(&matches);
+    let settings = unwrap_or_exit(get_settings(&matches));
+    if matches.is_present("print-config") {
+        print!(
+            "{}",
+            unwrap_or_exit(settings.to_toml().map_err(Error::from))
+        );
+        return;
+    }
+
+    let verbose = is_verbose(&matches);
+    let mut provider = unwrap_or_exit(get_provider(&matches));
     let mut consumer = get_consumer(&matches);
-    let mut transformers = get_transformers(&matches);
-    let repertoire = get_repertoire(matches.value_of("repertoire").unwrap()).unwrap();
-
-    let mut joinerator = Joinerator::new(Options {
-        allow_unreadable: matches.is_present("unreadable"),
-        limit: matches
-            .value_of("length")
-            .map(|v| v.parse::<usize>().unwrap()),
-        repertoire,
-        generator: vec![
-            GeneratorOptions {
-                category: GlyphPosition::ABOVE,
-                stacking: parse_stacking(matches.value_of("above:stacking").unwrap()).unwrap(),
-                frequency: parse_frequency(matches.value_of("above:frequency").unwrap()).unwrap(),
-            },
-            GeneratorOptions {
-                category: GlyphPosition::BELOW,
-                stacking: parse_stacking(matches.value_of("below:stacking").unwrap()).unwrap(),
-                frequency: parse_frequency(matches.value_of("below:frequency").unwrap()).unwrap(),
-            },
-            GeneratorOptions {
-                category: GlyphPosition::THROUGH,
-                stacking: parse_stacking(matches.value_of("through:stacking").unwrap()).unwrap(),
-                frequency: parse_frequency(matches.value_of("through:frequency").unwrap()).unwrap(),
-            },
-        ],
-    });
+    let mut transformers = unwrap_or_exit(settings.transformers().map_err(Error::from));
+    let format = unwrap_or_exit(settings.format().map_err(Error::from));
+    let repertoires = unwrap_or_exit(get_repertoires(&matches));
+    let options = unwrap_or_exit(settings.options(&repertoires).map_err(Error::from));
+
+    let mut joinerator = Joinerator::new(options);
+
+    // Run program.
+    let result = main_loop(
+        &mut provider,
+        &mut consumer,
+        |input| {
+            let transformed = format.map_visible(input, |text| {
+                transformers
+                    .iter_mut()
+                    .fold(Ok(text), |o, t| o.and_then(|v| t.transform(v)))
+            })?;
+
+            Ok(joinerator.process(&transformed))
+        },
+        verbose,
+        matches.is_present("watch"),
+    );
+
+    if result.is_err() {
+        main_errors(result.unwrap_err());
+        exit(1);
+    }
+}
+
+fn main_strip(matches: &ArgMatches) {
+    let verbose = is_verbose(matches);
+    let report = matches.is_present("report");
+    let mut provider = unwrap_or_exit(get_provider(matches));
+    let mut consumer = get_consumer(matches);
+    let repertoires = unwrap_or_exit(get_repertoires(matches));
+    let options = StripOptions {
+        preserve_diacritics: matches.is_present("preserve-diacritics"),
+        mode: if matches.is_present("all") {
+            StripMode::AllMarks
+        } else {
+            StripMode::Repertoire(unwrap_or_exit(get_repertoire(&repertoires, matches)))
+        },
+    };
 
     // Run program.
     let result = main_loop(
-        &mut joinerator,
         &mut provider,
         &mut consumer,
-        &mut transformers,
+        |input| {
+            let stripped = joinerator::strip(input, &options);
+            if report {
+                print_strip_report(&stripped);
+            }
+
+            Ok(stripped.text)
+        },
         verbose,
         matches.is_present("watch"),
     );
@@ -190,23 +355,97 @@ fn main() {
     }
 }
 
-fn main_loop(
-    joinerator: &mut Joinerator,
+#[cfg(feature = "server")]
+fn main_serve<'a>(matches: &'a ArgMatches<'a>, serve_matches: &'a ArgMatches<'a>) {
+    let service = unwrap_or_exit(get_service(matches, serve_matches));
+    let server = unwrap_or_exit(Server::bind(
+        serve_matches.value_of("bind").unwrap(),
+        service,
+    ));
+
+    if let Some(address) = server.address() {
+        eprintln!(
+            "{} http://{}",
+            COLORS.heading.paint("Listening on"),
+            COLORS.argument_value.paint(address.to_string())
+        );
+    }
+
+    unwrap_or_exit(server.run());
+}
+
+#[cfg(all(unix, feature = "daemon"))]
+fn main_daemon<'a>(matches: &'a ArgMatches<'a>, daemon_matches: &'a ArgMatches<'a>) {
+    let service = unwrap_or_exit(get_service(matches, daemon_matches));
+    let path = daemon_matches
+        .value_of("socket")
+        .map(PathBuf::from)
+        .unwrap_or_else(daemon::default_path);
+
+    let daemon = unwrap_or_exit(Daemon::bind(&path, service));
+    eprintln!(
+        "{} {}",
+        COLORS.heading.paint("Listening on"),
+        COLORS
+            .argument_value
+            .paint(daemon.path().display().to_string())
+    );
+
+    unwrap_or_exit(daemon.run());
+}
+
+#[cfg(feature = "interactive")]
+fn main_interactive<'a>(matches: &'a ArgMatches<'a>, interactive_matches: &'a ArgMatches<'a>) {
+    let service = unwrap_or_exit(get_service(matches, interactive_matches));
+    let text = interactive_matches
+        .values_of("values")
+        .map(|v| v.collect::<Vec<&str>>().join(" "))
+        .unwrap_or_default();
+
+    let mut interactive = Interactive::new(service, text);
+    unwrap_or_exit(interactive.run());
+}
+
+fn main_repl<'a>(matches: &'a ArgMatches<'a>, repl_matches: &'a ArgMatches<'a>) {
+    let config = unwrap_or_exit(Config::load().map_err(Error::from));
+    let repertoires = unwrap_or_exit(get_repertoires(repl_matches));
+
+    let mut settings = get_args_settings(matches, false);
+    settings.merge(&unwrap_or_exit(
+        config
+            .settings(repl_matches.value_of("preset"))
+            .map_err(Error::from),
+    ));
+
+    let mut repl = unwrap_or_exit(Repl::new(&repertoires, config, settings));
+
+    #[cfg(all(feature = "repl", not(windows)))]
+    {
+        if atty::is(Stream::Stdin) {
+            unwrap_or_exit(repl.run_editor());
+            return;
+        }
+    }
+
+    let stdin = std::io::stdin();
+    unwrap_or_exit(repl.run_plain(stdin.lock(), &mut std::io::stdout(), &mut std::io::stderr()));
+}
+
+fn main_loop<F>(
     provider: &mut Box<Provider>,
     consumer: &mut Box<Consumer>,
-    transformers: &mut Vec<Box<Transformer>>,
+    mut process: F,
     verbose: bool,
     watch: bool,
-) -> Result<(), Error> {
+) -> Result<(), Error>
+where
+    F: FnMut(&str) -> Result<String, Error>,
+{
     let mut more = true;
     while more {
         // Input processing.
         let input = (*provider).provide()?;
-        let transformed = transformers
-            .iter_mut()
-            .fold(Ok(input.clone()), |o, t| o.and_then(|v| t.transform(v)))?;
-
-        let processed = joinerator.process(&transformed);
+        let processed = process(&input)?;
 
         // Output to logs.
         if verbose {
@@ -224,12 +463,10 @@ fn main_loop(
         (*consumer).consume(processed)?;
         more = (*provider).has_more()?;
 
-        // If in watch mode, we'll poll every 10 milliseconds until a change happens.
-        // This isn't very efficient, but it works well enough.
+        // If in watch mode, we'll wait until the provider reports a change.
         if !more && watch {
             while !more {
-                sleep(Duration::from_millis(10));
-                more = (*provider).has_more()?;
+                more = (*provider).wait_for_change(WATCH_TIMEOUT)?;
             }
         }
     }
@@ -237,6 +474,16 @@ fn main_loop(
     Ok(())
 }
 
+fn unwrap_or_exit<T>(result: Result<T, Error>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(error) => {
+            main_errors(error);
+            exit(1);
+        }
+    }
+}
+
 fn main_errors(error: Error) -> () {
     eprintln!(
         "{}{}{}",
@@ -265,30 +512,168 @@ fn main_errors(error: Error) -> () {
     }
 }
 
-fn get_repertoire<'a>(name: &str) -> Option<&'a Repertoire> {
-    REPERTOIRES.get(name)
+fn get_settings<'a>(matches: &'a ArgMatches<'a>) -> Result<Settings, Error> {
+    let config = Config::load()?;
+
+    // Layered from lowest to highest priority: defaults, config, preset, command line.
+    let mut settings = get_args_settings(matches, false);
+    settings.merge(&config.settings(matches.value_of("preset"))?);
+    settings.merge(&get_args_settings(matches, true));
+    Ok(settings)
+}
+
+/// Creates a service for the `serve`, `daemon` and `interactive` commands.
+/// Requests are layered on top of the usual defaults, the configuration, and the chosen preset.
+#[cfg(any(
+    feature = "server",
+    feature = "interactive",
+    all(unix, feature = "daemon")
+))]
+fn get_service<'a>(
+    matches: &'a ArgMatches<'a>,
+    command_matches: &'a ArgMatches<'a>,
+) -> Result<Service, Error> {
+    let config = Config::load()?;
+    let repertoires = get_repertoires(command_matches)?;
+
+    let mut defaults = get_args_settings(matches, false);
+    defaults.merge(&config.settings(command_matches.value_of("preset"))?);
+    Ok(Service::new(repertoires, config, defaults))
 }
 
-fn get_transformers<'a>(matches: &'a ArgMatches<'a>) -> Vec<Box<Transformer>> {
-    if !matches.is_present("transform") {
-        return vec![];
+fn get_repertoires<'a>(matches: &'a ArgMatches<'a>) -> Result<RepertoireSet, Error> {
+    let mut repertoires = RepertoireSet::with_builtin();
+    repertoires.load_search_path(&repertoire::search_path(), |error| {
+        eprintln!(
+            "{} {}",
+            COLORS.error_heading.paint("Skipped repertoire:"),
+            COLORS.error.paint(error.to_string())
+        );
+    });
+
+    if let Some(files) = matches.values_of("repertoire-file") {
+        for file in files {
+            repertoires.load_file(file)?;
+        }
     }
 
-    matches
-        .values_of("transform")
-        .unwrap()
-        .into_iter()
-        .map(get_transformer)
-        .collect()
+    Ok(repertoires)
+}
+
+fn get_repertoire<'r, 'a>(
+    repertoires: &'r RepertoireSet,
+    matches: &'a ArgMatches<'a>,
+) -> Result<&'r Repertoire, Error> {
+    let name = matches.value_of("repertoire").unwrap();
+    repertoires.get(name).ok_or_else(|| {
+        format_err!(
+            "unknown repertoire '{}' (available: {})",
+            name,
+            repertoires.names().join(", ")
+        )
+    })
 }
 
 // -------------------------------------------------------------------------------------------------
 // Helper functions to convert command line arguments into objects.
 // -------------------------------------------------------------------------------------------------
 
-fn get_provider<'a>(matches: &'a ArgMatches<'a>) -> Box<Provider> {
-    match matches.value_of("input") {
-        Some("stdin") => Box::new(content::streams::StdinProvider::new()),
+fn is_verbose<'a>(matches: &'a ArgMatches<'a>) -> bool {
+    if matches.is_present("verbose") {
+        true
+    } else if matches.is_present("quiet") {
+        false
+    } else {
+        let outputs = matches
+            .values_of("output")
+            .into_iter()
+            .flatten()
+            .chain(matches.values_of("output-optional").into_iter().flatten());
+
+        !outputs.into_iter().any(|o| o == "stdout")
+    }
+}
+
+fn get_args_settings<'a>(matches: &'a ArgMatches<'a>, explicit: bool) -> Settings {
+    let value = |name: &str| {
+        if (matches.occurrences_of(name) > 0) == explicit {
+            matches.value_of(name).map(|v| v.to_owned())
+        } else {
+            None
+        }
+    };
+
+    let values = |name: &str| {
+        if (matches.occurrences_of(name) > 0) == explicit {
+            matches
+                .values_of(name)
+                .map(|v| v.map(|s| s.to_owned()).collect::<Vec<String>>())
+        } else {
+            None
+        }
+    };
+
+    let mut generator = BTreeMap::new();
+    for args in GENERATOR_ARGS {
+        let settings = GeneratorSettings {
+            frequency: value(args.frequency),
+            stacking: value(args.stacking).map(|v| parse_stacking(&v).unwrap()),
+        };
+
+        if settings != GeneratorSettings::default() {
+            generator.insert(args.position.name().to_owned(), settings);
+        }
+    }
+
+    Settings {
+        repertoire: value("repertoire"),
+        unreadable: if explicit && matches.is_present("unreadable") {
+            Some(true)
+        } else {
+            None
+        },
+        limit: value("length").map(|v| v.parse::<usize>().unwrap()),
+        limit_unit: value("limit-unit"),
+        limit_preset: value("limit-preset"),
+        seed: value("seed").map(|v| v.parse::<u64>().unwrap()),
+        density: value("density"),
+        format: value("format"),
+        canonical_order: if explicit && matches.is_present("canonical-order") {
+            Some(true)
+        } else {
+            None
+        },
+        normalize: value("normalize"),
+        protect: values("protect"),
+        protect_regex: values("protect-regex"),
+        transform: values("transform"),
+        generators: values("generator"),
+        generator,
+    }
+}
+
+fn get_provider<'a>(matches: &'a ArgMatches<'a>) -> Result<Box<Provider>, Error> {
+    let delimiter = matches
+        .value_of("stream")
+        .map(|name| RecordDelimiter::from_name(name).unwrap());
+
+    let input = matches.value_of("input");
+    if let Some(path) = input.and_then(|input| input.strip_prefix(FILE_PREFIX)) {
+        return Ok(match delimiter {
+            None => Box::new(content::file::FileProvider::new(path)),
+            Some(delimiter) => {
+                let file = File::open(path)
+                    .map_err(|e| format_err!("failed to read '{}': {}", path, e))?;
+                Box::new(content::streams::StreamProvider::new(file, delimiter))
+            }
+        });
+    }
+
+    Ok(match input {
+        Some("stdin") => match delimiter {
+            None => Box::new(content::streams::StdinProvider::new()),
+            Some(delimiter) => Box::new(content::streams::StreamProvider::stdin(delimiter)),
+        },
 
         Some("args") | Some("arguments") => {
             let args: LinkedList<String> = matches
@@ -301,32 +686,74 @@ fn get_provider<'a>(matches: &'a ArgMatches<'a>) -> Box<Provider> {
         }
 
         #[cfg(feature = "clipboard_support")]
-        Some("clipboard") => Box::new(content::clipboard::ClipboardProvider::new()),
+        Some("clipboard") => {
+            let max = matches.value_of("watch-interval").unwrap();
+            Box::new(content::clipboard::ClipboardProvider::new().with_backoff(
+                content::clipboard::DEFAULT_MIN_INTERVAL,
+                Duration::from_millis(max.parse::<u64>().unwrap()),
+            ))
+        }
 
         _ => panic!("Unsupported --input argument passed validation."),
-    }
+    })
 }
 
 fn get_consumer<'a>(matches: &'a ArgMatches<'a>) -> Box<Consumer> {
-    match matches.value_of("output") {
-        Some("stdout") => Box::new(content::streams::StdoutConsumer::new()),
-        Some("null") => Box::new(content::null::NullConsumer::new()),
+    let required: Vec<&str> = matches.values_of("output").unwrap().collect();
+    let optional: Vec<&str> = matches
+        .values_of("output-optional")
+        .map(|v| v.collect())
+        .unwrap_or_else(Vec::new);
 
-        #[cfg(feature = "clipboard_support")]
-        Some("clipboard") => Box::new(content::clipboard::ClipboardConsumer::new()),
+    if required.len() == 1 && optional.is_empty() {
+        return get_output_consumer(matches, required[0]);
+    }
 
-        _ => panic!("Unsupported --output argument passed validation."),
+    let mut tee = TeeConsumer::new().on_error(|error| {
+        eprintln!(
+            "{} {}",
+            COLORS.error_heading.paint("Output failed:"),
+            COLORS.error.paint(error.to_string())
+        );
+    });
+
+    for output in required {
+        tee = tee.with(get_output_consumer(matches, output), ErrorPolicy::FailFast);
     }
+
+    for output in optional {
+        tee = tee.with(
+            get_output_consumer(matches, output),
+            ErrorPolicy::BestEffort,
+        );
+    }
+
+    Box::new(tee)
 }
 
-fn get_transformer(name: &str) -> Box<Transformer> {
-    match name {
-        "upper" | "uppercase" => Box::new(transform::casing::TransformUpperCase::new()),
-        "lower" | "lowercase" => Box::new(transform::casing::TransformLowerCase::new()),
-        "randomcase" => Box::new(transform::casing::TransformRandomCase::new()),
-        "vowelcase" => Box::new(transform::casing::TransformVowelCase::new()),
-        "uwuize" => Box::new(transform::uwu::TransformUwuize::new()),
-        _ => panic!("Unsupported --transform argument passed validation."),
+fn get_output_consumer<'a>(matches: &'a ArgMatches<'a>, output: &str) -> Box<Consumer> {
+    if let Some(path) = output.strip_prefix(FILE_PREFIX) {
+        let mode = if matches.is_present("append") {
+            WriteMode::Append
+        } else if matches.is_present("atomic") {
+            WriteMode::Atomic
+        } else {
+            WriteMode::Overwrite
+        };
+
+        // When watching a whole file for changes, every output is a new version of the file.
+        let replace = matches.is_present("watch") && !matches.is_present("stream");
+        return Box::new(content::file::FileConsumer::new(path, mode).with_replace(replace));
+    }
+
+    match output {
+        "stdout" => Box::new(content::streams::StdoutConsumer::new()),
+        "null" => Box::new(content::null::NullConsumer::new()),
+
+        #[cfg(feature = "clipboard_support")]
+        "clipboard" => Box::new(content::clipboard::ClipboardConsumer::new()),
+
+        _ => panic!("Unsupported --output argument passed validation."),
     }
 }
 
@@ -334,32 +761,6 @@ fn get_transformer(name: &str) -> Box<Transformer> {
 // Helper functions to parse specific command line argument values.
 // -------------------------------------------------------------------------------------------------
 
-fn parse_frequency(str: &str) -> Option<GeneratorFrequency> {
-    if str.ends_with("%") {
-        match (&str[0..(str.len() - 1)]).parse::<f32>() {
-            Err(_) => None,
-            Ok(v) => {
-                if v > 0.0 && v <= 100.0 {
-                    Some(GeneratorFrequency::Percentage(v / 100.0))
-                } else {
-                    None
-                }
-            }
-        }
-    } else {
-        match str.parse::<usize>() {
-            Err(_) => None,
-            Ok(v) => {
-                if v > 0 {
-                    Some(GeneratorFrequency::Fixed(v))
-                } else {
-                    None
-                }
-            }
-        }
-    }
-}
-
 fn parse_stacking(str: &str) -> Option<usize> {
     match str.parse::<usize>() {
         Err(_) => None,
@@ -378,20 +779,40 @@ fn parse_stacking(str: &str) -> Option<usize> {
 // -------------------------------------------------------------------------------------------------
 
 fn handle_cli() -> ArgMatches<'static> {
-    let valid_reps: Vec<&'static str> = REPERTOIRES.keys().into_iter().map(|s| &s[..]).collect();
+    let valid_limit_presets: Vec<&'static str> = limit::PRESETS.iter().map(|p| p.name).collect();
+    let mut valid_protect: Vec<&'static str> = protect::NAMES.to_vec();
     let mut valid_input: Vec<&'static str> = vec!["stdin", "args", "arguments"];
     let mut valid_output: Vec<&'static str> = vec!["stdout", "null"];
 
+    valid_protect.push("all");
+
+    let generator_help: Vec<(String, String)> = GENERATOR_ARGS
+        .iter()
+        .map(|g| {
+            (
+                format!(
+                    "Specifies the maximum number symbols that can {} a glyph.",
+                    g.description
+                ),
+                format!(
+                    "Specifies the frequency of the symbols that can {} a glyph.",
+                    g.description
+                ),
+            )
+        })
+        .collect();
+
     #[cfg(feature = "clipboard_support")]
     {
         valid_input.push("clipboard");
         valid_output.push("clipboard");
     }
 
-    App::new("joinerator")
+    let app = App::new("joinerator")
         .version(env!("CARGO_PKG_VERSION"))
         .author("Ethan P. <eth-p@hidden.email>")
         .about(env!("CARGO_PKG_DESCRIPTION"))
+        .setting(AppSettings::ArgsNegateSubcommands)
         .arg(
             Arg::with_name("repertoire")
                 .short("z")
@@ -399,15 +820,18 @@ fn handle_cli() -> ArgMatches<'static> {
                 .help("Specifies which character repertoire to use.")
                 .value_name("NAME")
                 .takes_value(true)
-                .default_value("default")
-                .possible_values(&valid_reps[..]),
+                .default_value("default"),
         )
+        .arg(repertoire_file_arg())
         .arg(
             Arg::with_name("length")
                 .short("l")
                 .long("length")
                 .help("Enforces a maximum string length.")
-                .long_help("Enforces a maximum string length. This will not truncate the text.")
+                .long_help(
+                    "Enforces a maximum string length. This will not truncate the text. \
+                     The length is measured in the unit given by --limit-unit.",
+                )
                 .alias("limit")
                 .value_name("LENGTH")
                 .takes_value(true)
@@ -424,93 +848,139 @@ fn handle_cli() -> ArgMatches<'static> {
                 }),
         )
         .arg(
-            Arg::with_name("above:stacking")
-                .short("a")
-                .long("above:stacking")
-                .help("Specifies the maximum number symbols that can go above a glyph.")
-                .alias("above-stacking")
-                .value_name("COUNT")
+            Arg::with_name("limit-unit")
+                .long("limit-unit")
+                .help("Specifies the unit used to measure the string length.")
+                .long_help(
+                    "Specifies the unit used to measure the string length. \
+                     Graphemes only count the input, since marks never add a grapheme cluster.",
+                )
+                .value_name("UNIT")
                 .takes_value(true)
-                .default_value("1")
-                .validator(|v| {
-                    parse_stacking(&v)
-                        .and(Some(()))
-                        .ok_or("Invalid stacking size.".to_owned())
-                }),
+                .possible_values(limit::UNIT_NAMES),
         )
         .arg(
-            Arg::with_name("above:frequency")
-                .short("A")
-                .long("above:frequency")
-                .help("Specifies the frequency of the symbols that can go above a glyph.")
-                .alias("above-frequency")
-                .value_name("FREQUENCY")
+            Arg::with_name("limit-preset")
+                .long("limit-preset")
+                .help("Enforces the maximum string length of a platform.")
+                .value_name("PRESET")
                 .takes_value(true)
-                .default_value("60%")
-                .validator(|v| {
-                    parse_frequency(&v)
-                        .and(Some(()))
-                        .ok_or("Invalid frequency.".to_owned())
-                }),
+                .possible_values(&valid_limit_presets[..]),
         )
         .arg(
-            Arg::with_name("below:stacking")
-                .short("b")
-                .long("below:stacking")
-                .help("Specifies the maximum number symbols that can go below a glyph.")
-                .alias("below-stacking")
-                .value_name("COUNT")
+            Arg::with_name("seed")
+                .long("seed")
+                .help("Specifies the seed used for random number generation.")
+                .long_help(
+                    "Specifies the seed used for random number generation. \
+                     The same input, options, and seed will always produce the same output.",
+                )
+                .value_name("SEED")
                 .takes_value(true)
-                .default_value("1")
                 .validator(|v| {
-                    parse_stacking(&v)
-                        .and(Some(()))
-                        .ok_or("Invalid stacking size.".to_owned())
+                    v.parse::<u64>()
+                        .and(Ok(()))
+                        .or(Err("Seed provided is not a positive integer.".to_owned()))
                 }),
         )
         .arg(
-            Arg::with_name("below:frequency")
-                .short("B")
-                .long("below:frequency")
-                .help("Specifies the frequency of the symbols that can go below a glyph.")
-                .alias("above-frequency")
-                .value_name("FREQUENCY")
+            Arg::with_name("generator")
+                .short("g")
+                .long("generator")
+                .help("Adds a generator for a position of symbols.")
+                .long_help(
+                    "Adds a generator for a position of symbols. This can be repeated, \
+                     and replaces the generators from the --above:stacking style options. \
+                     The format is POSITION:FREQUENCY:STACKING[:DENSITY] (e.g. below:30%:2).",
+                )
+                .value_name("GENERATOR")
                 .takes_value(true)
-                .default_value("60%")
+                .number_of_values(1)
+                .multiple(true)
                 .validator(|v| {
-                    parse_frequency(&v)
+                    GeneratorOptions::parse(&v, &Density::default())
                         .and(Some(()))
-                        .ok_or("Invalid frequency.".to_owned())
+                        .ok_or("Invalid generator.".to_owned())
                 }),
         )
+        .args(&generator_args(&generator_help))
         .arg(
-            Arg::with_name("through:stacking")
-                .short("c")
-                .long("through:stacking")
-                .help("Specifies the maximum number symbols that can go through a glyph.")
-                .alias("through-stacking")
-                .value_name("COUNT")
+            Arg::with_name("density")
+                .short("d")
+                .long("density")
+                .help("Specifies where in the text the symbols are most likely to appear.")
+                .long_help(
+                    "Specifies where in the text the symbols are most likely to appear. \
+                     This can be one of uniform, ramp-up, ramp-down, wave[:PERIODS], center, \
+                     edges, or a comma-separated list of weights (e.g. 0,1,0.5).",
+                )
+                .value_name("CURVE")
                 .takes_value(true)
-                .default_value("0")
+                .default_value("uniform")
                 .validator(|v| {
-                    parse_stacking(&v)
+                    Density::parse(&v)
                         .and(Some(()))
-                        .ok_or("Invalid stacking size.".to_owned())
+                        .ok_or("Invalid density.".to_owned())
                 }),
         )
         .arg(
-            Arg::with_name("through:frequency")
-                .short("C")
-                .long("through:frequency")
-                .help("Specifies the frequency of the symbols that can go through a glyph.")
-                .alias("through-frequency")
-                .value_name("FREQUENCY")
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .help("Specifies the format of the text.")
+                .long_help(
+                    "Specifies the format of the text. \
+                     Only the visible text will be transformed or have symbols added to it.",
+                )
+                .value_name("FORMAT")
+                .takes_value(true)
+                .default_value("plain")
+                .possible_values(markup::NAMES),
+        )
+        .arg(
+            Arg::with_name("canonical-order")
+                .long("canonical-order")
+                .help("Sorts the symbols on each character by their canonical combining class.")
+                .long_help(
+                    "Sorts the symbols on each character by their canonical combining class. \
+                     This prevents them from being rearranged when the text is normalized.",
+                ),
+        )
+        .arg(
+            Arg::with_name("normalize")
+                .long("normalize")
+                .help("Normalizes the text to a Unicode normalization form.")
+                .long_help(
+                    "Normalizes the text to a Unicode normalization form. \
+                     Symbols that would be changed by normalization are never added.",
+                )
+                .value_name("FORM")
+                .takes_value(true)
+                .possible_values(normalize::NAMES),
+        )
+        .arg(
+            Arg::with_name("protect")
+                .short("p")
+                .long("protect")
+                .help("Prevents symbols from being added to certain parts of the text.")
+                .value_name("SPANS")
+                .takes_value(true)
+                .number_of_values(1)
+                .multiple(true)
+                .possible_values(&valid_protect[..]),
+        )
+        .arg(
+            Arg::with_name("protect-regex")
+                .long("protect-regex")
+                .help("Prevents symbols from being added to text matching a regular expression.")
+                .value_name("REGEX")
                 .takes_value(true)
-                .default_value("10%")
+                .number_of_values(1)
+                .multiple(true)
                 .validator(|v| {
-                    parse_frequency(&v)
-                        .and(Some(()))
-                        .ok_or("Invalid frequency.".to_owned())
+                    Regex::new(&v)
+                        .and(Ok(()))
+                        .map_err(|e| format!("Invalid regular expression: {}", e))
                 }),
         )
         .arg(
@@ -522,35 +992,31 @@ fn handle_cli() -> ArgMatches<'static> {
                 .takes_value(true)
                 .number_of_values(1)
                 .multiple(true)
-                .possible_values(&[
-                    "uppercase",
-                    "lowercase",
-                    "randomcase",
-                    "vowelcase",
-                    "upper",
-                    "lower",
-                    "uwuize",
-                ]),
+                .possible_values(transform::NAMES),
         )
         .arg(
-            Arg::with_name("list-repertoires")
-                .long("list-repertoires")
-                .help("Lists all the available character repertoires.")
-                .conflicts_with_all(&["repertoire"]),
+            Arg::with_name("preset")
+                .short("P")
+                .long("preset")
+                .help("Uses the settings of a preset from the configuration file.")
+                .long_help(
+                    "Uses the settings of a preset from the configuration file. \
+                     Configuration is read from $XDG_CONFIG_HOME/joinerator/config.toml \
+                     and the closest .joinerator.toml, and command line options take priority.",
+                )
+                .value_name("NAME")
+                .takes_value(true),
         )
         .arg(
-            Arg::with_name("quiet")
-                .short("q")
-                .long("quiet")
-                .help("Suppresses non-essential messages.")
-                .conflicts_with("verbose"),
+            Arg::with_name("print-config")
+                .long("print-config")
+                .help("Prints the effective settings as a configuration file."),
         )
         .arg(
-            Arg::with_name("verbose")
-                .short("v")
-                .long("verbose")
-                .help("Display non-essential messages.")
-                .conflicts_with("quiet"),
+            Arg::with_name("list-repertoires")
+                .long("list-repertoires")
+                .help("Lists all the available character repertoires.")
+                .conflicts_with_all(&["repertoire"]),
         )
         .arg(
             Arg::with_name("unreadable")
@@ -558,45 +1024,355 @@ fn handle_cli() -> ArgMatches<'static> {
                 .long("unreadable")
                 .help("Allows unreadable character combinations."),
         )
-        .arg(
-            Arg::with_name("watch")
-                .short("W")
-                .long("watch")
-                .help("Watches for changes over time (when using mutable input sources)."),
-        )
-        .arg(
-            Arg::with_name("input")
-                .short("i")
-                .long("input")
-                .help("Specifies the input source.")
-                .takes_value(true)
-                .value_name("TYPE")
-                .possible_values(&valid_input[..])
-                .default_value("stdin"),
-        )
-        .arg(
-            Arg::with_name("output")
-                .short("o")
-                .long("output")
-                .help("Specifies the output destination.")
-                .value_name("TYPE")
-                .takes_value(true)
-                .possible_values(&valid_output[..])
-                .default_value("stdout"),
+        .args(&io_args(&valid_input, &valid_output))
+        .subcommand(
+            SubCommand::with_name("strip")
+                .about("Removes combining marks from text.")
+                .arg(
+                    Arg::with_name("repertoire")
+                        .short("z")
+                        .long("repertoire")
+                        .help("Specifies which character repertoire to remove.")
+                        .value_name("NAME")
+                        .takes_value(true)
+                        .default_value("default"),
+                )
+                .arg(repertoire_file_arg())
+                .arg(
+                    Arg::with_name("all")
+                        .long("all")
+                        .help("Removes all nonspacing marks instead of a repertoire."),
+                )
+                .arg(
+                    Arg::with_name("preserve-diacritics")
+                        .short("p")
+                        .long("preserve-diacritics")
+                        .help("Preserves canonical diacritics (such as \"é\")."),
+                )
+                .arg(
+                    Arg::with_name("report")
+                        .short("r")
+                        .long("report")
+                        .help("Reports how many marks were removed from each character."),
+                )
+                .args(&io_args(&valid_input, &valid_output)),
+        );
+
+    #[cfg(feature = "server")]
+    let app = app.subcommand(
+        SubCommand::with_name("serve")
+            .about("Serves a JSON API over HTTP.")
+            .long_about(
+                "Serves a JSON API over HTTP. \
+                 POST /process joinerates text, POST /strip removes combining marks, \
+                 and GET /repertoires lists the available repertoires.",
+            )
+            .arg(
+                Arg::with_name("bind")
+                    .long("bind")
+                    .help("Specifies the address to listen on.")
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .default_value(server::DEFAULT_ADDRESS),
+            )
+            .arg(
+                Arg::with_name("preset")
+                    .short("P")
+                    .long("preset")
+                    .help("Specifies the preset used for every request.")
+                    .value_name("NAME")
+                    .takes_value(true),
+            )
+            .arg(repertoire_file_arg()),
+    );
+
+    #[cfg(all(unix, feature = "daemon"))]
+    let app = app.subcommand(
+        SubCommand::with_name("daemon")
+            .about("Serves requests over a Unix domain socket.")
+            .long_about(
+                "Serves requests over a Unix domain socket. \
+                 Every line sent to the socket is a JSON object with the text and its settings \
+                 (e.g. {\"text\": \"hello\", \"preset\": \"spooky\"}), \
+                 and is answered by a line with a JSON object containing the processed text.",
+            )
+            .arg(
+                Arg::with_name("socket")
+                    .long("socket")
+                    .help("Specifies the path of the socket.")
+                    .long_help(
+                        "Specifies the path of the socket. \
+                         This defaults to $XDG_RUNTIME_DIR/joinerator.sock.",
+                    )
+                    .value_name("PATH")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("preset")
+                    .short("P")
+                    .long("preset")
+                    .help("Specifies the preset used for every request.")
+                    .value_name("NAME")
+                    .takes_value(true),
+            )
+            .arg(repertoire_file_arg()),
+    );
+
+    let app = app.subcommand(
+        SubCommand::with_name("repl")
+            .about("Joinerates lines as they are typed.")
+            .long_about(
+                "Joinerates lines as they are typed. \
+                 Lines starting with ':' are commands that change the settings \
+                 (e.g. ':set above.frequency 80%', ':transform +uwuize', or ':seed 42'). \
+                 Type ':help' to see every command.",
+            )
+            .arg(
+                Arg::with_name("preset")
+                    .short("P")
+                    .long("preset")
+                    .help("Specifies the preset to start from.")
+                    .value_name("NAME")
+                    .takes_value(true),
+            )
+            .arg(repertoire_file_arg()),
+    );
+
+    #[cfg(feature = "interactive")]
+    let app = app.subcommand(
+        SubCommand::with_name("interactive")
+            .about("Tunes the settings with a live preview.")
+            .long_about(
+                "Tunes the settings with a live preview. \
+                 Type to change the text, use the arrow keys to adjust the settings, \
+                 Ctrl-S to save them as a preset, and Ctrl-Y to copy the output.",
+            )
+            .arg(
+                Arg::with_name("preset")
+                    .short("P")
+                    .long("preset")
+                    .help("Specifies the preset to start from.")
+                    .value_name("NAME")
+                    .takes_value(true),
+            )
+            .arg(repertoire_file_arg())
+            .arg(
+                Arg::with_name("values")
+                    .help("The initial text.")
+                    .multiple(true),
+            ),
+    );
+
+    app.get_matches()
+}
+
+fn generator_args<'a>(help: &'a [(String, String)]) -> Vec<Arg<'static, 'a>> {
+    let mut args = vec![];
+    for (generator, (stacking_help, frequency_help)) in GENERATOR_ARGS.iter().zip(help.iter()) {
+        let mut stacking = Arg::with_name(generator.stacking)
+            .long(generator.stacking)
+            .help(stacking_help)
+            .alias(generator.stacking_alias)
+            .value_name("COUNT")
+            .takes_value(true)
+            .validator(|v| {
+                parse_stacking(&v)
+                    .and(Some(()))
+                    .ok_or("Invalid stacking size.".to_owned())
+            });
+
+        let mut frequency = Arg::with_name(generator.frequency)
+            .long(generator.frequency)
+            .help(frequency_help)
+            .alias(generator.frequency_alias)
+            .value_name("FREQUENCY")
+            .takes_value(true)
+            .validator(|v| {
+                GeneratorFrequency::parse(&v)
+                    .and(Some(()))
+                    .ok_or("Invalid frequency.".to_owned())
+            });
+
+        if let Some((short_stacking, short_frequency)) = generator.short {
+            stacking = stacking.short(short_stacking);
+            frequency = frequency.short(short_frequency);
+        }
+
+        if let Some((default_stacking, default_frequency)) = generator.default {
+            stacking = stacking.default_value(default_stacking);
+            frequency = frequency.default_value(default_frequency);
+        }
+
+        args.push(stacking);
+        args.push(frequency);
+    }
+
+    args
+}
+
+fn repertoire_file_arg() -> Arg<'static, 'static> {
+    Arg::with_name("repertoire-file")
+        .long("repertoire-file")
+        .help("Loads an extra character repertoire from a YAML file.")
+        .long_help(
+            "Loads an extra character repertoire from a YAML file. \
+             Repertoires are also loaded from $JOINERATOR_REPERTOIRE_PATH \
+             and $XDG_DATA_HOME/joinerator/repertoires.",
         )
-        .arg(Arg::with_name("values").value_name("INPUT").multiple(true))
-        .get_matches()
+        .value_name("PATH")
+        .takes_value(true)
+        .number_of_values(1)
+        .multiple(true)
+}
+
+fn io_args(
+    valid_input: &[&'static str],
+    valid_output: &[&'static str],
+) -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .help("Suppresses non-essential messages.")
+            .conflicts_with("verbose"),
+        Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .help("Display non-essential messages.")
+            .conflicts_with("quiet"),
+        Arg::with_name("watch")
+            .short("W")
+            .long("watch")
+            .help("Watches for changes over time (when using mutable input sources)."),
+        Arg::with_name("watch-interval")
+            .long("watch-interval")
+            .help("Specifies the longest time between checks for clipboard changes.")
+            .long_help(
+                "Specifies the longest time between checks for clipboard changes, \
+                 in milliseconds. Files are watched for changes without checking.",
+            )
+            .value_name("MILLIS")
+            .takes_value(true)
+            .default_value("1000")
+            .validator(|v| {
+                v.parse::<u64>().and(Ok(())).or(Err(
+                    "Interval provided is not a positive integer.".to_owned()
+                ))
+            }),
+        Arg::with_name("input")
+            .short("i")
+            .long("input")
+            .help("Specifies the input source.")
+            .takes_value(true)
+            .value_name("TYPE")
+            .long_help(
+                "Specifies the input source. \
+                 This can be stdin, args, clipboard (if supported), or file:PATH.",
+            )
+            .validator(io_validator(valid_input))
+            .default_value("stdin"),
+        Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .help("Specifies the output destination.")
+            .value_name("TYPE")
+            .takes_value(true)
+            .long_help(
+                "Specifies the output destination. \
+                 This can be stdout, null, clipboard (if supported), or file:PATH. \
+                 It can be repeated to write to several destinations.",
+            )
+            .validator(io_validator(valid_output))
+            .number_of_values(1)
+            .multiple(true)
+            .default_value("stdout"),
+        Arg::with_name("output-optional")
+            .long("output-optional")
+            .help("Specifies an output destination that is allowed to fail.")
+            .long_help(
+                "Specifies an output destination that is allowed to fail. \
+                 Errors are reported, but the other destinations are still written to.",
+            )
+            .value_name("TYPE")
+            .takes_value(true)
+            .number_of_values(1)
+            .multiple(true)
+            .validator(io_validator(valid_output)),
+        Arg::with_name("append")
+            .long("append")
+            .help("Appends to the output file instead of overwriting it.")
+            .conflicts_with("atomic"),
+        Arg::with_name("atomic")
+            .long("atomic")
+            .help("Replaces the output file atomically every time it is written.")
+            .long_help(
+                "Replaces the output file atomically every time it is written. \
+                 This is safe to use when the input and output are the same file.",
+            )
+            .conflicts_with("append"),
+        Arg::with_name("stream")
+            .short("s")
+            .long("stream")
+            .help("Processes the input one record at a time, as it arrives.")
+            .long_help(
+                "Processes the input one record at a time, as it arrives. \
+                 Records can be separated by lines, paragraphs, or NUL characters.",
+            )
+            .value_name("RECORD")
+            .takes_value(true)
+            .possible_values(content::streams::DELIMITER_NAMES),
+        Arg::with_name("values").value_name("INPUT").multiple(true),
+    ]
 }
 
-fn list_repertoires() {
+fn io_validator(valid: &[&'static str]) -> impl Fn(String) -> Result<(), String> {
+    let valid = valid.to_vec();
+    move |v| {
+        let path = v.strip_prefix(FILE_PREFIX);
+        if valid.contains(&&v[..]) || path.is_some_and(|path| !path.is_empty()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "Unsupported value. Expected one of: {}, {}PATH",
+                valid.join(", "),
+                FILE_PREFIX
+            ))
+        }
+    }
+}
+
+fn list_repertoires(repertoires: &RepertoireSet) {
     println!("{}", COLORS.heading.paint("Repertoires:"));
-    for rep in REPERTOIRES.values() {
+    for name in repertoires.names() {
+        let rep = repertoires.get(name).unwrap();
+        let source = match repertoires.source(name) {
+            Some(path) => format!(" ({})", path.display()),
+            None => String::new(),
+        };
+
         println!(
-            "{}{:width$} -- {}",
+            "{}{:width$} -- {}{}",
             COLORS.argument_value.paint(&rep.name),
             "",
             &rep.description,
-            width = 16 - rep.name.len()
+            COLORS.description.paint(source),
+            width = 16usize.saturating_sub(rep.name.len())
         );
     }
 }
+
+fn print_strip_report(stripped: &joinerator::Stripped) {
+    let removed: Vec<String> = stripped
+        .removed
+        .iter()
+        .filter(|(_, n)| *n > 0)
+        .map(|(c, n)| format!("{}{}", c, COLORS.argument_value.paint(format!("×{}", n))))
+        .collect();
+
+    eprintln!(
+        "{} {} {}",
+        COLORS.heading.paint("Removed:"),
+        stripped.total_removed(),
+        removed.join(" ")
+    );
+}
@@ -0,0 +1,327 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use std::ops::Range;
+
+use failure::Error;
+use regex::Regex;
+// -------------------------------------------------------------------------------------------------
+
+lazy_static! {
+    static ref REGEX_ANSI: Regex = Regex::new(concat!(
+        "\x1B\\[[0-?]*[ -/]*[@-~]",                // CSI sequences (e.g. colors).
+        "|\x1B\\][^\x07\x1B]*(?:\x07|\x1B\\\\)?", // OSC sequences (e.g. hyperlinks).
+        "|\x1B[ -/]*[0-~]",                       // Other escape sequences.
+    ))
+    .unwrap();
+    static ref REGEX_HTML: Regex = Regex::new(concat!(
+        "(?is)<!--.*?-->",                      // Comments.
+        "|<script\\b.*?</script\\s*>",          // Scripts.
+        "|<style\\b.*?</style\\s*>",            // Stylesheets.
+        "|<[!?/]?[a-z][^>]*>",                  // Tags and declarations.
+        "|&(?:[a-z][a-z0-9]*|#[0-9]+|#x[0-9a-f]+);", // Entities.
+    ))
+    .unwrap();
+    static ref REGEX_MARKDOWN: Regex = Regex::new(concat!(
+        "(?m)^ {0,3}(?:```|~~~)[^\\n]*\\n(?s:.*?)^ {0,3}(?:```|~~~)[^\\n]*$", // Code blocks.
+        "|`+[^`\\n]+`+",                                     // Inline code.
+        "|^ {0,3}\\[[^\\]\\n]+\\]:[^\\n]*$",                  // Link reference definitions.
+        "|\\]\\([^)\\n]*\\)|\\]\\[[^\\]\\n]*\\]|!?\\[|\\]",  // Link syntax and destinations.
+        "|<(?:[a-zA-Z][a-zA-Z0-9+.-]*:[^>\\s]*|/?[a-zA-Z][^>\\n]*)>", // Autolinks and inline HTML.
+        "|\\\\[!-/:-@\\[-`{-~]",                             // Backslash escapes.
+        "|^ {0,3}#{1,6}(?:[ \\t]|$)|[ \\t]+#+[ \\t]*$",       // Headings.
+        "|^ {0,3}(?:[-*_][ \\t]*){3,}$",                      // Thematic breaks.
+        "|^[ \\t]*(?:>[ \\t]?)+",                             // Block quotes.
+        "|^[ \\t]*(?:[-*+]|[0-9]{1,9}[.)])[ \\t]+(?:\\[[ xX]\\][ \\t]+)?", // List items.
+        "|^ {0,3}(?:=+|-+)[ \\t]*$",                          // Setext heading underlines.
+        "|\\*+|_+|~+|\\|",                                    // Emphasis and tables.
+    ))
+    .unwrap();
+    static ref REGEX_MARKDOWN_TABLE_DELIMITER: Regex = Regex::new(concat!(
+        "(?m)^ {0,3}\\|?[ \\t]*:?-+:?[ \\t]*(?:\\|[ \\t]*:?-+:?[ \\t]*)*\\|[ \\t]*$", // Leading pipe.
+        "|^ {0,3}\\|?[ \\t]*:?-+:?[ \\t]*(?:\\|[ \\t]*:?-+:?[ \\t]*)+$",           // Inner pipes.
+    ))
+    .unwrap();
+}
+
+/// The format of the input text.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Format {
+    /// Plain text, where every character is visible.
+    #[default]
+    Plain,
+
+    /// Markdown, where syntax, link destinations, and code are not visible text.
+    Markdown,
+
+    /// HTML, where tags, entities, comments, scripts, and styles are not visible text.
+    Html,
+
+    /// Text containing ANSI escape sequences, which are not visible text.
+    Ansi,
+}
+
+/// The names of every format that can be created with `Format::from_name`.
+pub const NAMES: &[&str] = &["plain", "markdown", "html", "ansi"];
+
+// -------------------------------------------------------------------------------------------------
+
+impl Format {
+    /// Creates a format from its name (see `NAMES`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "plain" | "text" => Some(Format::Plain),
+            "markdown" | "md" => Some(Format::Markdown),
+            "html" => Some(Format::Html),
+            "ansi" => Some(Format::Ansi),
+            _ => None,
+        }
+    }
+
+    /// Finds the spans of a document that are markup rather than visible text.
+    ///
+    /// ## Arguments
+    /// - `input` - The document.
+    ///
+    /// ## Returns
+    /// The sorted, non-overlapping byte ranges of every markup span.
+    pub fn markup_spans(self, input: &str) -> Vec<Range<usize>> {
+        let regex: &Regex = match self {
+            Format::Plain => return vec![],
+            Format::Markdown => return markdown_spans(input),
+            Format::Html => &REGEX_HTML,
+            Format::Ansi => &REGEX_ANSI,
+        };
+
+        regex
+            .find_iter(input)
+            .map(|m| m.start()..m.end())
+            .filter(|r| !r.is_empty())
+            .collect()
+    }
+
+    /// Applies a function to every span of visible text in a document.
+    /// The markup is copied to the output unchanged.
+    ///
+    /// ## Arguments
+    /// - `input` - The document.
+    /// - `f`     - The function to apply to each span of visible text.
+    ///
+    /// ## Returns
+    /// The document with every span of visible text replaced.
+    pub fn map_visible<F>(self, input: &str, mut f: F) -> Result<String, Error>
+    where
+        F: FnMut(String) -> Result<String, Error>,
+    {
+        if self == Format::Plain {
+            return f(input.to_owned());
+        }
+
+        let mut output = String::with_capacity(input.len());
+        let mut offset = 0;
+        for span in self.markup_spans(input) {
+            if span.start > offset {
+                output.push_str(&f(input[offset..span.start].to_owned())?);
+            }
+
+            output.push_str(&input[span.clone()]);
+            offset = span.end;
+        }
+
+        if offset < input.len() {
+            output.push_str(&f(input[offset..].to_owned())?);
+        }
+
+        Ok(output)
+    }
+}
+
+/// Finds the spans of a Markdown document that are markup rather than visible text.
+///
+/// Underscores are only markup when they aren't inside a word (e.g. `snake_case`), and pipes are
+/// only markup in the rows of a table.
+fn markdown_spans(input: &str) -> Vec<Range<usize>> {
+    let tables = markdown_tables(input);
+    let delimiters: Vec<Range<usize>> = REGEX_MARKDOWN_TABLE_DELIMITER
+        .find_iter(input)
+        .map(|m| m.start()..m.end())
+        .filter(|r| tables.iter().any(|t| t.start <= r.start && r.end <= t.end))
+        .collect();
+
+    let mut spans: Vec<Range<usize>> = REGEX_MARKDOWN
+        .find_iter(input)
+        .filter(|m| match m.as_str() {
+            "|" => tables.iter().any(|t| t.contains(&m.start())),
+            text if text.starts_with('_') => !is_intraword(input, m.start()..m.end()),
+            _ => true,
+        })
+        .map(|m| m.start()..m.end())
+        .filter(|r| !r.is_empty())
+        .filter(|r| {
+            !delimiters
+                .iter()
+                .any(|d| d.start <= r.start && r.end <= d.end)
+        })
+        .chain(delimiters.iter().cloned())
+        .collect();
+
+    spans.sort_by_key(|r| r.start);
+    spans
+}
+
+/// Finds the tables in a Markdown document.
+/// A table is a header row, a delimiter row (e.g. `|---|:-:|`), and every following row until a
+/// line without a pipe.
+///
+/// ## Returns
+/// The byte ranges of every table.
+fn markdown_tables(input: &str) -> Vec<Range<usize>> {
+    let mut tables = Vec::new();
+    for delimiter in REGEX_MARKDOWN_TABLE_DELIMITER.find_iter(input) {
+        let header_end = match input[..delimiter.start()].strip_suffix('\n') {
+            Some(before) => before.len(),
+            None => continue,
+        };
+
+        let header_start = input[..header_end].rfind('\n').map_or(0, |i| i + 1);
+        if !input[header_start..header_end].contains('|') {
+            continue;
+        }
+
+        let mut end = delimiter.end();
+        while input[end..].starts_with('\n') {
+            let row_end = input[end + 1..]
+                .find('\n')
+                .map_or(input.len(), |i| end + 1 + i);
+            if !input[end + 1..row_end].contains('|') {
+                break;
+            }
+
+            end = row_end;
+        }
+
+        tables.push(header_start..end);
+    }
+
+    tables
+}
+
+/// Checks if a span of a string is surrounded by alphanumeric characters on both sides.
+fn is_intraword(input: &str, span: Range<usize>) -> bool {
+    let before = input[..span.start].chars().next_back();
+    let after = input[span.end..].chars().next();
+    before.is_some_and(char::is_alphanumeric) && after.is_some_and(char::is_alphanumeric)
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Uppercases the visible text of a document.
+    fn visible(format: Format, input: &str) -> String {
+        format
+            .map_visible(input, |text| Ok(text.to_uppercase()))
+            .unwrap()
+    }
+
+    #[test]
+    fn from_name() {
+        for name in NAMES {
+            assert!(Format::from_name(name).is_some(), "{}", name);
+        }
+
+        assert_eq!(Format::from_name("md"), Some(Format::Markdown));
+        assert_eq!(Format::default(), Format::Plain);
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(visible(Format::Plain, "<b>*hi*</b>"), "<B>*HI*</B>");
+    }
+
+    #[test]
+    fn markdown() {
+        assert_eq!(
+            visible(
+                Format::Markdown,
+                "# Title\n- **bold** [link](http://x.y) `code`\n\n```\nblock\n```"
+            ),
+            "# TITLE\n- **BOLD** [LINK](http://x.y) `code`\n\n```\nblock\n```"
+        );
+    }
+
+    #[test]
+    fn markdown_intraword_underscores() {
+        assert_eq!(
+            markdown_spans("a snake_case_name"),
+            Vec::<Range<usize>>::new()
+        );
+        assert_eq!(
+            visible(Format::Markdown, "_em_ and __init__ or snake_case"),
+            "_EM_ AND __INIT__ OR SNAKE_CASE"
+        );
+        assert_eq!(markdown_spans("_a_"), vec![0..1, 2..3]);
+    }
+
+    #[test]
+    fn markdown_pipes_in_prose() {
+        assert_eq!(markdown_spans("this | that"), Vec::<Range<usize>>::new());
+        assert_eq!(
+            markdown_spans("a | b\nnot a table"),
+            Vec::<Range<usize>>::new()
+        );
+    }
+
+    #[test]
+    fn markdown_tables() {
+        let input = "| a | b |\n|---|:-:|\n| c | d |\nc | d\n\ne | f";
+        assert_eq!(
+            visible(Format::Markdown, input),
+            "| A | B |\n|---|:-:|\n| C | D |\nC | D\n\nE | F"
+        );
+
+        let spans = markdown_spans(input);
+        assert!(spans.contains(&(10..19)));
+        assert_eq!(
+            spans.iter().filter(|s| &input[(*s).clone()] == "|").count(),
+            7
+        );
+        assert_eq!(markdown_spans("a | b\n--|--"), vec![2..3, 6..11]);
+    }
+
+    #[test]
+    fn markdown_with_inline_html() {
+        assert_eq!(
+            visible(Format::Markdown, "<span title=\"x\">*hi*</span>"),
+            "<span title=\"x\">*HI*</span>"
+        );
+    }
+
+    #[test]
+    fn html() {
+        assert_eq!(
+            visible(
+                Format::Html,
+                "<div class=\"a\"><b><i>nested</i> text</b>&amp;<!-- note --></div>"
+            ),
+            "<div class=\"a\"><b><i>NESTED</i> TEXT</b>&amp;<!-- note --></div>"
+        );
+        assert_eq!(
+            visible(Format::Html, "<script>var a;</script><style>p {}</style>x"),
+            "<script>var a;</script><style>p {}</style>X"
+        );
+    }
+
+    #[test]
+    fn ansi() {
+        assert_eq!(
+            visible(
+                Format::Ansi,
+                "\x1B[1m\x1B[31mred\x1B[0m \x1B]8;;http://x.y\x1B\\link\x1B]8;;\x1B\\"
+            ),
+            "\x1B[1m\x1B[31mRED\x1B[0m \x1B]8;;http://x.y\x1B\\LINK\x1B]8;;\x1B\\"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::UnicodeNormalization;
+// -------------------------------------------------------------------------------------------------
+
+/// A Unicode normalization form.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Normalization {
+    /// Canonical composition (NFC).
+    Nfc,
+
+    /// Canonical decomposition (NFD).
+    Nfd,
+}
+
+/// The names of every normalization form that can be created with `Normalization::from_name`.
+pub const NAMES: &[&str] = &["nfc", "nfd"];
+
+// -------------------------------------------------------------------------------------------------
+
+impl Normalization {
+    /// Creates a normalization form from its name (see `NAMES`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nfc" => Some(Normalization::Nfc),
+            "nfd" => Some(Normalization::Nfd),
+            _ => None,
+        }
+    }
+
+    /// Gets the name of the normalization form.
+    pub fn name(self) -> &'static str {
+        match self {
+            Normalization::Nfc => "nfc",
+            Normalization::Nfd => "nfd",
+        }
+    }
+
+    /// Normalizes a string.
+    ///
+    /// ## Arguments
+    /// - `input` - The string to normalize.
+    pub fn apply(self, input: &str) -> String {
+        match self {
+            Normalization::Nfc => input.nfc().collect(),
+            Normalization::Nfd => input.nfd().collect(),
+        }
+    }
+
+    /// Checks if a combining glyph is left unchanged by the normalization form.
+    /// Glyphs such as U+0340 are always replaced by normalization.
+    ///
+    /// ## Arguments
+    /// - `glyph` - The combining glyph.
+    pub fn is_stable(self, glyph: char) -> bool {
+        let mut buffer = [0; 4];
+        let str = glyph.encode_utf8(&mut buffer);
+        self.apply(str) == *str
+    }
+}
+
+/// Sorts every run of combining marks by their canonical combining class.
+/// Marks with the same class keep their order, and marks are never moved past a starter.
+///
+/// This is the canonical ordering used by Unicode normalization, so a string in canonical order
+/// will not have its marks rearranged by NFC or NFD.
+///
+/// ## Arguments
+/// - `input` - The string to reorder.
+pub fn canonical_order(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut marks: Vec<(u8, char)> = vec![];
+
+    for c in input.chars() {
+        let class = canonical_combining_class(c);
+        if class != 0 {
+            marks.push((class, c));
+            continue;
+        }
+
+        marks.sort_by_key(|m| m.0);
+        output.extend(marks.drain(..).map(|m| m.1));
+        output.push(c);
+    }
+
+    marks.sort_by_key(|m| m.0);
+    output.extend(marks.drain(..).map(|m| m.1));
+    output
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::joinerator::{GeneratorFrequency, Joinerator, Options, OptionsBuilder};
+    use crate::repertoire::{self, GlyphPosition};
+    use unicode_normalization::{is_nfc, is_nfd};
+
+    const INPUT: &str = "Cafe\u{0301} na\u{00EF}ve r\u{00E9}sum\u{00E9}, Hello world!";
+
+    /// Creates options with a generator for every position used by the default repertoire.
+    fn builder(seed: u64) -> OptionsBuilder<'static> {
+        let mut builder = Options::builder(repertoire::get("default").unwrap()).seed(seed);
+        for position in &[
+            GlyphPosition::ABOVE,
+            GlyphPosition::BELOW,
+            GlyphPosition::THROUGH,
+            GlyphPosition::DOUBLE,
+            GlyphPosition::ABOVE_RIGHT,
+        ] {
+            builder = builder.generator(*position, GeneratorFrequency::Percentage(1.0), 3);
+        }
+
+        builder
+    }
+
+    #[test]
+    fn from_name() {
+        for name in NAMES {
+            assert_eq!(Normalization::from_name(name).unwrap().name(), *name);
+        }
+
+        assert_eq!(Normalization::from_name("nfkc"), None);
+    }
+
+    #[test]
+    fn canonical_order_sorts_marks_between_starters() {
+        // Below (220) sorts before above (230), but never past the starter `b`.
+        assert_eq!(
+            canonical_order("a\u{0301}\u{0316}b\u{0300}"),
+            "a\u{0316}\u{0301}b\u{0300}"
+        );
+        assert_eq!(canonical_order("\u{0301}\u{0316}"), "\u{0316}\u{0301}");
+    }
+
+    #[test]
+    fn normalize_nfc() {
+        let mut double = false;
+        for seed in 0..20 {
+            let options = builder(seed).normalize(Normalization::Nfc).build();
+            let output = Joinerator::new(options).process(INPUT);
+
+            assert!(is_nfc(&output), "seed {}: {:?}", seed, output);
+            assert_eq!(Normalization::Nfc.apply(&output), output);
+            double |= output
+                .chars()
+                .any(|c| ('\u{035C}'..='\u{0362}').contains(&c));
+        }
+
+        assert!(double, "no double marks were generated");
+    }
+
+    #[test]
+    fn normalize_nfd() {
+        for seed in 0..20 {
+            let options = builder(seed).normalize(Normalization::Nfd).build();
+            let output = Joinerator::new(options).process(INPUT);
+
+            assert!(is_nfd(&output), "seed {}: {:?}", seed, output);
+            assert_eq!(Normalization::Nfd.apply(&output), output);
+        }
+    }
+
+    #[test]
+    fn canonical_order_survives_normalization() {
+        let input = Normalization::Nfd.apply(INPUT);
+        for seed in 0..20 {
+            let options = builder(seed).canonical_order(true).build();
+            let output = Joinerator::new(options).process(&input);
+
+            assert!(is_nfd(&output), "seed {}: {:?}", seed, output);
+            assert_eq!(Normalization::Nfd.apply(&output), output);
+            assert_eq!(
+                Normalization::Nfd.apply(&Normalization::Nfc.apply(&output)),
+                output
+            );
+        }
+    }
+}
@@ -0,0 +1,219 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::markup::Format;
+// -------------------------------------------------------------------------------------------------
+
+lazy_static! {
+    static ref REGEX_URL: Regex =
+        Regex::new("(?i)\\b(?:[a-z][a-z0-9+.-]*://|www\\.)[^\\s<>]*[^\\s<>.,;:!?'\")\\]]").unwrap();
+    static ref REGEX_MENTION: Regex = Regex::new("<@[!&]?\\d+>|\\B@\\w(?:[\\w.-]*\\w)?").unwrap();
+    static ref REGEX_CHANNEL: Regex = Regex::new("<#\\d+>|\\B#\\w[\\w-]*").unwrap();
+    static ref REGEX_SHORTCODE: Regex = Regex::new(":[a-zA-Z0-9_+-]+:").unwrap();
+    static ref REGEX_CODE: Regex = Regex::new("```[\\s\\S]*?```|`[^`\\n]+`").unwrap();
+}
+
+/// A detector for spans of text that should never be modified.
+#[derive(Debug, Clone)]
+pub enum Protector {
+    /// URLs (e.g. `https://example.com` or `www.example.com`).
+    Urls,
+
+    /// User mentions (e.g. `@someone` or `<@1234>`).
+    Mentions,
+
+    /// Channel references (e.g. `#general` or `<#1234>`).
+    Channels,
+
+    /// Emoji shortcodes (e.g. `:smile:`).
+    Shortcodes,
+
+    /// Inline code and code blocks surrounded by backticks.
+    InlineCode,
+
+    /// Spans matched by a user-supplied regular expression.
+    Pattern(Regex),
+
+    /// Markup that isn't visible text (e.g. HTML tags or ANSI escape sequences).
+    Markup(Format),
+}
+
+/// The names of every built-in protector that can be created with `Protector::from_name`.
+pub const NAMES: &[&str] = &["urls", "mentions", "channels", "shortcodes", "code"];
+
+// -------------------------------------------------------------------------------------------------
+
+impl Protector {
+    /// Creates a built-in protector from its name (see `NAMES`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "urls" => Some(Protector::Urls),
+            "mentions" => Some(Protector::Mentions),
+            "channels" => Some(Protector::Channels),
+            "shortcodes" => Some(Protector::Shortcodes),
+            "code" => Some(Protector::InlineCode),
+            _ => None,
+        }
+    }
+
+    /// Creates every built-in protector.
+    pub fn all() -> Vec<Self> {
+        NAMES.iter().map(|n| Self::from_name(n).unwrap()).collect()
+    }
+
+    /// Finds the protected spans in a string.
+    ///
+    /// ## Arguments
+    /// - `input` - The input string.
+    ///
+    /// ## Returns
+    /// The byte ranges of every protected span.
+    pub fn find_spans(&self, input: &str) -> Vec<Range<usize>> {
+        let regex: &Regex = match self {
+            Protector::Urls => &REGEX_URL,
+            Protector::Mentions => &REGEX_MENTION,
+            Protector::Channels => &REGEX_CHANNEL,
+            Protector::Shortcodes => &REGEX_SHORTCODE,
+            Protector::InlineCode => &REGEX_CODE,
+            Protector::Pattern(regex) => regex,
+            Protector::Markup(format) => return format.markup_spans(input),
+        };
+
+        regex
+            .find_iter(input)
+            .map(|m| m.start()..m.end())
+            .filter(|r| !r.is_empty())
+            .collect()
+    }
+}
+
+/// Finds the protected spans in a string for several protectors.
+///
+/// ## Arguments
+/// - `protectors` - The protectors.
+/// - `input`      - The input string.
+///
+/// ## Returns
+/// The sorted byte ranges of every protected span. Ranges may overlap.
+pub fn find_spans(protectors: &[Protector], input: &str) -> Vec<Range<usize>> {
+    let mut spans: Vec<Range<usize>> = protectors
+        .iter()
+        .flat_map(|p| p.find_spans(input))
+        .collect();
+
+    spans.sort_by_key(|r| r.start);
+    spans
+}
+
+/// Checks if a range overlaps any of the protected spans.
+///
+/// ## Arguments
+/// - `spans` - The sorted protected spans (see `find_spans`).
+/// - `range` - The byte range to check.
+pub fn is_protected(spans: &[Range<usize>], range: &Range<usize>) -> bool {
+    spans
+        .iter()
+        .take_while(|s| s.start < range.end)
+        .any(|s| s.end > range.start)
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::joinerator::{GeneratorFrequency, Joinerator, Options};
+    use crate::repertoire::{self, GlyphPosition};
+
+    fn matches<'a>(protector: &Protector, input: &'a str) -> Vec<&'a str> {
+        protector
+            .find_spans(input)
+            .into_iter()
+            .map(|r| &input[r])
+            .collect()
+    }
+
+    #[test]
+    fn from_name_creates_every_protector() {
+        assert_eq!(Protector::all().len(), NAMES.len());
+        assert!(Protector::from_name("emails").is_none());
+    }
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            matches(
+                &Protector::Urls,
+                "see https://example.com/a?b=c, (www.example.org) or ftp://x.y."
+            ),
+            vec!["https://example.com/a?b=c", "www.example.org", "ftp://x.y"]
+        );
+    }
+
+    #[test]
+    fn mentions() {
+        assert_eq!(
+            matches(
+                &Protector::Mentions,
+                "hi @some.one, <@!123> and me@example.com"
+            ),
+            vec!["@some.one", "<@!123>"]
+        );
+    }
+
+    #[test]
+    fn channels() {
+        assert_eq!(
+            matches(&Protector::Channels, "#general and <#42>, not a#b"),
+            vec!["#general", "<#42>"]
+        );
+    }
+
+    #[test]
+    fn shortcodes() {
+        assert_eq!(
+            matches(&Protector::Shortcodes, "nice :+1: :smile_cat: at 10:30"),
+            vec![":+1:", ":smile_cat:"]
+        );
+    }
+
+    #[test]
+    fn code() {
+        assert_eq!(
+            matches(&Protector::InlineCode, "run `ls` or\n```\nrm -rf\n```"),
+            vec!["`ls`", "```\nrm -rf\n```"]
+        );
+    }
+
+    #[test]
+    fn pattern() {
+        let protector = Protector::Pattern(Regex::new("\\bTODO\\b").unwrap());
+        assert_eq!(matches(&protector, "TODO: not TODOS"), vec!["TODO"]);
+    }
+
+    #[test]
+    fn is_protected_checks_overlap() {
+        let spans = find_spans(&[Protector::Mentions, Protector::Channels], "#a @b");
+        assert_eq!(spans, vec![0..2, 3..5]);
+        assert!(is_protected(&spans, &(1..2)));
+        assert!(!is_protected(&spans, &(2..3)));
+        assert!(is_protected(&spans, &(4..6)));
+    }
+
+    #[test]
+    fn protected_text_is_unchanged() {
+        let options = Options::builder(repertoire::get("default").unwrap())
+            .generator(GlyphPosition::ABOVE, GeneratorFrequency::Percentage(1.0), 2)
+            .protect(Protector::Urls)
+            .seed(3)
+            .build();
+
+        let output = Joinerator::new(options).process("go to https://example.com now");
+        assert!(output.contains(" https://example.com "));
+        assert!(!output.starts_with("go to"));
+    }
+}
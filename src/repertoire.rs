@@ -3,31 +3,32 @@
 // -------------------------------------------------------------------------------------------------
 use failure::Fail;
 use regex::Regex;
-use serde::Deserialize;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
 use serde_regex;
-
-#[cfg(not(cached))]
 use serde_yaml;
+use unicode_normalization::char::decompose_compatible;
 
 #[cfg(not(cached))]
 use serde::Serialize;
 
-#[cfg(not(cached))]
-use std::path::Path;
-
-#[cfg(not(cached))]
+use std::collections::HashMap;
+use std::env;
 use std::fs;
+use std::path::{Path, PathBuf};
 
 // -------------------------------------------------------------------------------------------------
 
+/// A repertoire of combining glyphs.
 #[cfg_attr(not(cached), derive(Serialize))]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct Repertoire {
     pub name: String,
     pub description: String,
     pub glyphs: Vec<Glyph>,
 }
 
+/// A combining glyph.
 #[cfg_attr(not(cached), derive(Serialize))]
 #[derive(Debug, Deserialize, Clone)]
 pub struct Glyph {
@@ -36,25 +37,296 @@ pub struct Glyph {
 
     #[serde(with = "serde_regex")]
     pub combines: Regex,
+
+    /// How likely the glyph is to be picked, relative to the other glyphs.
+    /// A glyph with a weight of `0.5` is picked half as often as a glyph with the default of `1.0`.
+    #[serde(default = "default_weight", deserialize_with = "deserialize_weight")]
+    pub weight: f32,
 }
 
+/// The position of a combining glyph relative to the glyph it is combined with.
 #[cfg_attr(not(cached), derive(Serialize))]
-#[derive(Debug, Deserialize, Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[allow(non_camel_case_types)]
 pub enum GlyphPosition {
     ABOVE,
     BELOW,
     THROUGH,
+
+    /// Marks that span both the glyph and the glyph after it (e.g. U+035C to U+0362).
+    DOUBLE,
+
+    /// Marks that surround the glyph (e.g. the enclosing circle U+20DD).
+    ENCLOSING,
+
+    /// Marks drawn over the whole glyph (e.g. the ring overlay U+20D8).
+    OVERLAY,
+
+    /// Marks attached to the top right of the glyph (e.g. the horn U+031B).
+    ABOVE_RIGHT,
+
+    /// Marks that change how the glyph is presented (e.g. the variation selectors).
+    VARIATION,
 }
 
+/// An error encountered while loading a repertoire.
 #[derive(Debug, Fail)]
 #[allow(dead_code)]
 pub enum Error {
     #[fail(display = "failed to deserialize: {}", reason)]
     DeserializeError { reason: String },
+
+    #[fail(display = "failed to read '{}': {}", path, reason)]
+    ReadError { path: String, reason: String },
+}
+
+/// A collection of repertoires, indexed by name.
+/// Repertoires added later will replace existing repertoires with the same name.
+pub struct RepertoireSet {
+    repertoires: HashMap<String, Repertoire>,
+    sources: HashMap<String, PathBuf>,
+}
+
+/// The environment variable containing extra directories to load repertoires from.
+pub const PATH_VAR: &str = "JOINERATOR_REPERTOIRE_PATH";
+
+/// The CBOR-serialized cache of built-in repertoires.
+/// This is a map of repertoire names to repertoires, generated from `res/rep_*.yaml` at build time.
+#[cfg(cached)]
+pub const CACHE: &[u8] = include_bytes!("repertoire.cache");
+
+#[cfg(cached)]
+lazy_static! {
+    static ref BUILTIN: HashMap<String, Repertoire> = from_cache(CACHE).unwrap();
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Deserializes a CBOR repertoire cache.
+///
+/// ## Arguments
+/// - `data` - The cache data (e.g. `CACHE`).
+///
+/// ## Returns
+/// A map of repertoire names to repertoires.
+#[cfg(cached)]
+pub fn from_cache(data: &[u8]) -> Result<HashMap<String, Repertoire>, Error> {
+    serde_cbor::from_slice(data).map_err(|e| Error::DeserializeError {
+        reason: e.to_string(),
+    })
+}
+
+/// Gets the built-in repertoires.
+///
+/// ## Returns
+/// A map of repertoire names to repertoires.
+#[cfg(cached)]
+pub fn builtin() -> &'static HashMap<String, Repertoire> {
+    &BUILTIN
+}
+
+/// Gets a built-in repertoire by its name.
+///
+/// ## Arguments
+/// - `name` - The repertoire name.
+#[cfg(cached)]
+pub fn get(name: &str) -> Option<&'static Repertoire> {
+    BUILTIN.get(name)
+}
+
+/// The default weight of a glyph.
+fn default_weight() -> f32 {
+    1.0
+}
+
+/// Deserializes the weight of a glyph, which must be finite and non-negative.
+fn deserialize_weight<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let weight = f32::deserialize(deserializer)?;
+    if weight.is_finite() && weight >= 0.0 {
+        Ok(weight)
+    } else {
+        Err(D::Error::custom(format!("invalid weight of {}", weight)))
+    }
+}
+
+/// Gets the directories that user repertoires are loaded from.
+///
+/// This is every directory in `$JOINERATOR_REPERTOIRE_PATH`, followed by
+/// `$XDG_DATA_HOME/joinerator/repertoires` (or `~/.local/share/joinerator/repertoires`).
+pub fn search_path() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = env::var_os(PATH_VAR)
+        .map(|v| env::split_paths(&v).collect())
+        .unwrap_or_default();
+
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+
+    if let Some(data_home) = data_home {
+        paths.push(data_home.join("joinerator").join("repertoires"));
+    }
+
+    paths
+}
+
+/// Finds the repertoire files (`*.yaml` or `*.yml`) in a directory, sorted by path.
+/// Directories that do not exist have no files.
+fn repertoire_files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    if !path.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let read_error = |e: std::io::Error| Error::ReadError {
+        path: path.to_string_lossy().into_owned(),
+        reason: e.to_string(),
+    };
+
+    let mut files: Vec<PathBuf> = fs::read_dir(path)
+        .map_err(read_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_error)?
+        .into_iter()
+        .map(|entry| entry.path())
+        .filter(|p| {
+            matches!(
+                p.extension().and_then(|e| e.to_str()),
+                Some("yml") | Some("yaml")
+            )
+        })
+        .collect();
+
+    files.sort();
+    Ok(files)
 }
 
 // -------------------------------------------------------------------------------------------------
 
+impl RepertoireSet {
+    /// Creates an empty repertoire set.
+    pub fn new() -> Self {
+        RepertoireSet {
+            repertoires: HashMap::new(),
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Creates a repertoire set containing the built-in repertoires.
+    #[cfg(cached)]
+    pub fn with_builtin() -> Self {
+        RepertoireSet {
+            repertoires: BUILTIN.clone(),
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Adds a repertoire to the set.
+    pub fn insert(&mut self, repertoire: Repertoire) {
+        self.sources.remove(&repertoire.name);
+        self.repertoires.insert(repertoire.name.clone(), repertoire);
+    }
+
+    /// Loads a repertoire file and adds it to the set.
+    ///
+    /// ## Arguments
+    /// - `path` - The path to the YAML file.
+    ///
+    /// ## Returns
+    /// The name of the loaded repertoire.
+    pub fn load_file<P>(&mut self, path: P) -> Result<String, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let repertoire = Repertoire::from_file(path.as_ref())?;
+        let name = repertoire.name.clone();
+        self.insert(repertoire);
+        self.sources
+            .insert(name.clone(), path.as_ref().to_path_buf());
+        Ok(name)
+    }
+
+    /// Loads every repertoire file (`*.yaml` or `*.yml`) in a directory and adds them to the set.
+    /// Directories that do not exist are ignored.
+    ///
+    /// ## Arguments
+    /// - `path` - The path to the directory.
+    ///
+    /// ## Returns
+    /// The names of the loaded repertoires.
+    pub fn load_dir<P>(&mut self, path: P) -> Result<Vec<String>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        repertoire_files(path.as_ref())?
+            .iter()
+            .map(|file| self.load_file(file))
+            .collect()
+    }
+
+    /// Loads every repertoire file in a search path (e.g. from `search_path`).
+    /// Files and directories that can't be loaded are skipped, since they may not be used.
+    ///
+    /// ## Arguments
+    /// - `paths`    - The directories to load from, with earlier directories taking priority.
+    /// - `on_error` - A function called with the error for every skipped file or directory.
+    ///
+    /// ## Returns
+    /// The names of the loaded repertoires.
+    pub fn load_search_path<P, F>(&mut self, paths: &[P], mut on_error: F) -> Vec<String>
+    where
+        P: AsRef<Path>,
+        F: FnMut(Error),
+    {
+        let mut names = vec![];
+        for path in paths.iter().rev() {
+            let path = path.as_ref();
+            let files = match repertoire_files(path) {
+                Ok(files) => files,
+                Err(error) => {
+                    on_error(error);
+                    continue;
+                }
+            };
+
+            for file in files {
+                match self.load_file(&file) {
+                    Ok(name) => names.push(name),
+                    Err(error) => on_error(error),
+                }
+            }
+        }
+
+        names
+    }
+
+    /// Gets a repertoire by its name.
+    pub fn get(&self, name: &str) -> Option<&Repertoire> {
+        self.repertoires.get(name)
+    }
+
+    /// Gets the file a repertoire was loaded from.
+    /// This is `None` for built-in repertoires.
+    pub fn source(&self, name: &str) -> Option<&Path> {
+        self.sources.get(name).map(|p| p.as_path())
+    }
+
+    /// Gets the names of every repertoire in the set, sorted alphabetically.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.repertoires.keys().map(|s| &s[..]).collect();
+        names.sort();
+        names
+    }
+}
+
+impl Default for RepertoireSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Repertoire {
     /// Creates a repertoire.
     ///
@@ -62,7 +334,6 @@ impl Repertoire {
     /// - `name`       - The repertoire name.
     /// - `description - The repertoire description.
     /// - `glyphs`     - A vector of glyphs for the repertoire.
-    #[cfg(not(cached))]
     pub fn new(name: String, description: String, glyphs: Vec<Glyph>) -> Self {
         Repertoire {
             name,
@@ -72,14 +343,13 @@ impl Repertoire {
     }
 
     /// Creates a repertoire by deserializing YAML data.
-    #[cfg(not(cached))]
     pub fn from_yaml(data: &str) -> Result<Self, Error> {
         serde_yaml::from_str::<Repertoire>(data).map_err(|e| Error::DeserializeError {
             reason: e.to_string(),
         })
     }
 
-    #[cfg(not(cached))]
+    /// Creates a repertoire by reading a YAML file.
     pub fn from_file<P>(path: P) -> Result<Self, Error>
     where
         P: AsRef<Path>,
@@ -87,8 +357,9 @@ impl Repertoire {
         let path_buf = path.as_ref();
         let path_ext = path_buf.extension().map(|s| s.to_str().unwrap());
         let path_name = path_buf.file_name().unwrap().to_str().unwrap();
-        let data = fs::read_to_string(path_buf).map_err(|e| Error::DeserializeError {
-            reason: format!("io error: {}", e.to_string()),
+        let data = fs::read_to_string(path_buf).map_err(|e| Error::ReadError {
+            path: path_buf.to_string_lossy().into_owned(),
+            reason: e.to_string(),
         })?;
 
         if path_ext.is_none() {
@@ -98,7 +369,10 @@ impl Repertoire {
         }
 
         match path_ext {
-            Some("yml") | Some("yaml") => Ok(Self::from_yaml(&data)?),
+            Some("yml") | Some("yaml") => Self::from_yaml(&data).map_err(|e| Error::ReadError {
+                path: path_buf.to_string_lossy().into_owned(),
+                reason: e.to_string(),
+            }),
             _ => Err(Error::DeserializeError {
                 reason: format!("unable to deserialize the file type of '{}'", path_name),
             }),
@@ -106,6 +380,42 @@ impl Repertoire {
     }
 }
 
+impl GlyphPosition {
+    /// Every glyph position.
+    pub const ALL: &'static [GlyphPosition] = &[
+        GlyphPosition::ABOVE,
+        GlyphPosition::BELOW,
+        GlyphPosition::THROUGH,
+        GlyphPosition::DOUBLE,
+        GlyphPosition::ENCLOSING,
+        GlyphPosition::OVERLAY,
+        GlyphPosition::ABOVE_RIGHT,
+        GlyphPosition::VARIATION,
+    ];
+
+    /// Gets the lowercase name of the glyph position.
+    pub fn name(self) -> &'static str {
+        match self {
+            GlyphPosition::ABOVE => "above",
+            GlyphPosition::BELOW => "below",
+            GlyphPosition::THROUGH => "through",
+            GlyphPosition::DOUBLE => "double",
+            GlyphPosition::ENCLOSING => "enclosing",
+            GlyphPosition::OVERLAY => "overlay",
+            GlyphPosition::ABOVE_RIGHT => "above-right",
+            GlyphPosition::VARIATION => "variation",
+        }
+    }
+
+    /// Gets a glyph position from its name (case-insensitive).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|p| p.name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+}
+
 impl Glyph {
     /// Creates a new glyph definition.
     ///
@@ -113,18 +423,148 @@ impl Glyph {
     /// - `codepoint` - The char representing the glyph.
     /// - `position`  - The position of the glyph relative to other glyphs.
     /// - `combines`  - A regular expression for what other glyphs this will combine onto.
-    #[cfg(not(cached))]
     pub fn new(codepoint: char, position: GlyphPosition, combines: Regex) -> Self {
         Glyph {
             codepoint,
             position,
             combines,
+            weight: default_weight(),
         }
     }
 
+    /// Sets how likely the glyph is to be picked, relative to the other glyphs.
+    /// Weights that are negative or not finite are replaced with `0.0`, so the glyph is never
+    /// picked.
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = if weight.is_finite() && weight > 0.0 {
+            weight
+        } else {
+            0.0
+        };
+
+        self
+    }
+
     /// Checks if the combining glyph can be applied to a specific glyph.
+    /// Stylized letters (e.g. `𝐚` or `ⓐ`) are also checked as the letter they decompose to.
+    /// Characters that decompose to several characters (e.g. `½` or `ﬁ`) are not.
     pub fn is_applicable(&self, c: char) -> bool {
         let mut buffer = [0; 4];
-        self.combines.is_match(c.encode_utf8(&mut buffer))
+        if self.combines.is_match(c.encode_utf8(&mut buffer)) {
+            return true;
+        }
+
+        let mut decomposed = vec![];
+        decompose_compatible(c, |d| decomposed.push(d));
+
+        match decomposed[..] {
+            [base] if base != c => self.combines.is_match(base.encode_utf8(&mut buffer)),
+            _ => false,
+        }
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = "name: test\ndescription: Test\nglyphs:\n  - codepoint: \"\\u0300\"\n    combines: '\\w'\n    position: ABOVE\n";
+
+    /// Creates an empty temporary directory.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("joinerator-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn from_yaml() {
+        let repertoire = Repertoire::from_yaml(YAML).unwrap();
+        assert_eq!(repertoire.name, "test");
+        assert_eq!(repertoire.glyphs[0].codepoint, '\u{0300}');
+        assert_eq!(repertoire.glyphs[0].position, GlyphPosition::ABOVE);
+        assert!(repertoire.glyphs[0].is_applicable('a'));
+        assert!(!repertoire.glyphs[0].is_applicable(' '));
+    }
+
+    #[test]
+    fn is_applicable_to_stylized_letters() {
+        let glyph = Glyph::new(
+            '\u{0300}',
+            GlyphPosition::ABOVE,
+            Regex::new("[a-z]").unwrap(),
+        );
+
+        assert!(glyph.is_applicable('a'));
+        assert!(glyph.is_applicable('\u{1D41A}'));
+        assert!(glyph.is_applicable('\u{24D0}'));
+        assert!(!glyph.is_applicable('A'));
+        assert!(!glyph.is_applicable('\u{1D400}'));
+        assert!(!glyph.is_applicable('\u{00BD}'));
+        assert!(!glyph.is_applicable('\u{FB01}'));
+    }
+
+    #[test]
+    fn load_dir_fails_on_bad_files() {
+        let dir = temp_dir("load-dir");
+        fs::write(dir.join("good.yaml"), YAML).unwrap();
+        fs::write(dir.join("bad.yml"), "glyphs: [").unwrap();
+        fs::write(dir.join("notes.txt"), "not a repertoire").unwrap();
+
+        assert!(RepertoireSet::new().load_dir(&dir).is_err());
+        fs::remove_file(dir.join("bad.yml")).unwrap();
+        assert_eq!(RepertoireSet::new().load_dir(&dir).unwrap(), vec!["test"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_search_path_skips_bad_files() {
+        let dir = temp_dir("search-path");
+        fs::write(dir.join("a_bad.yaml"), "glyphs: [").unwrap();
+        fs::write(dir.join("b_good.yaml"), YAML).unwrap();
+        let paths = [dir.clone(), dir.join("missing")];
+
+        let mut repertoires = RepertoireSet::new();
+        let mut errors = vec![];
+        let names = repertoires.load_search_path(&paths, |error| errors.push(error.to_string()));
+
+        assert_eq!(names, vec!["test"]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("a_bad.yaml"), "{}", errors[0]);
+        assert_eq!(
+            repertoires.source("test"),
+            Some(dir.join("b_good.yaml").as_path())
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn weights() {
+        let weighted = YAML.replace("position: ABOVE", "position: ABOVE\n    weight: 0.25");
+        assert_eq!(
+            Repertoire::from_yaml(&weighted).unwrap().glyphs[0].weight,
+            0.25
+        );
+        assert_eq!(Repertoire::from_yaml(YAML).unwrap().glyphs[0].weight, 1.0);
+
+        for weight in &["-1", ".nan", ".inf"] {
+            let invalid = YAML.replace(
+                "position: ABOVE",
+                &format!("position: ABOVE\n    weight: {}", weight),
+            );
+            assert!(Repertoire::from_yaml(&invalid).is_err(), "{}", weight);
+        }
+    }
+
+    #[test]
+    fn with_weight_replaces_invalid_weights() {
+        let glyph = Glyph::new('\u{0300}', GlyphPosition::ABOVE, Regex::new(".").unwrap());
+        assert_eq!(glyph.clone().with_weight(2.0).weight, 2.0);
+        assert_eq!(glyph.clone().with_weight(-1.0).weight, 0.0);
+        assert_eq!(glyph.clone().with_weight(std::f32::NAN).weight, 0.0);
+        assert_eq!(glyph.with_weight(std::f32::INFINITY).weight, 0.0);
     }
 }
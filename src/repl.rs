@@ -0,0 +1,434 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+#[cfg(feature = "repl")]
+use std::io;
+use std::io::{BufRead, Write};
+
+use failure::{err_msg, Error};
+
+#[cfg(feature = "repl")]
+use rustyline::error::ReadlineError;
+#[cfg(feature = "repl")]
+use rustyline::DefaultEditor;
+
+use crate::config::{Config, Settings};
+use crate::joinerator::Joinerator;
+use crate::markup::Format;
+use crate::repertoire::RepertoireSet;
+use crate::strip::{self, StripMode, StripOptions};
+use crate::transform::{self, Transformer};
+// -------------------------------------------------------------------------------------------------
+
+/// The prompt shown when reading lines with line editing.
+pub const PROMPT: &str = "> ";
+
+/// The commands that can be used, and their descriptions.
+pub const COMMANDS: &[(&str, &str)] = &[
+    (
+        ":set NAME VALUE",
+        "Changes a setting (e.g. `:set above.frequency 80%`).",
+    ),
+    (":unset NAME", "Removes a setting."),
+    (":repertoire NAME", "Changes the repertoire."),
+    (
+        ":transform [+NAME|-NAME|NAMES]",
+        "Adds, removes, or replaces transformers.",
+    ),
+    (":seed [SEED]", "Changes the seed, or uses a random seed."),
+    (":preset NAME", "Applies a preset."),
+    (
+        ":strip",
+        "Switches between joinerating and stripping lines.",
+    ),
+    (":show", "Shows the current settings."),
+    (":help", "Shows the commands."),
+    (":quit", "Quits."),
+];
+
+/// A read-eval-print loop that processes one line at a time.
+///
+/// Lines are processed with a single `Joinerator`, which is only recreated when the settings are
+/// changed. Lines that start with `:` are commands (see `COMMANDS`), and lines that start with
+/// `::` are processed without their first `:`.
+pub struct Repl<'a> {
+    repertoires: &'a RepertoireSet,
+    config: Config,
+    settings: Settings,
+    strip: bool,
+    state: Option<State<'a>>,
+}
+
+/// The result of handling a line.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Reply {
+    /// A processed line.
+    Output(String),
+
+    /// A message about a command.
+    Message(String),
+
+    /// Nothing to show.
+    Empty,
+
+    /// The user asked to quit.
+    Quit,
+}
+
+/// Everything created from the settings.
+struct State<'a> {
+    joinerator: Joinerator<'a>,
+    transformers: Vec<Box<dyn Transformer>>,
+    format: Format,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl<'a> Repl<'a> {
+    /// Creates a REPL.
+    ///
+    /// ## Arguments
+    /// - `repertoires` - The repertoires that can be used.
+    /// - `config`      - The configuration, which provides the presets.
+    /// - `settings`    - The initial settings.
+    pub fn new(
+        repertoires: &'a RepertoireSet,
+        config: Config,
+        settings: Settings,
+    ) -> Result<Self, Error> {
+        let mut repl = Repl {
+            repertoires,
+            config,
+            settings: Settings::default(),
+            strip: false,
+            state: None,
+        };
+
+        repl.apply(settings)?;
+        Ok(repl)
+    }
+
+    /// Handles a single line, which is either text to process or a command.
+    ///
+    /// ## Arguments
+    /// - `line` - The line, without its line ending.
+    pub fn handle(&mut self, line: &str) -> Result<Reply, Error> {
+        if line.starts_with("::") {
+            return self.process(&line[1..]).map(Reply::Output);
+        }
+
+        let command = match line.strip_prefix(':') {
+            Some(command) => command,
+            None => return self.process(line).map(Reply::Output),
+        };
+
+        let mut parts = command.trim().splitn(2, char::is_whitespace);
+        let command = parts.next().unwrap_or("");
+        let args = parts.next().map(|s| s.trim()).unwrap_or("");
+        let arg = if args.is_empty() { None } else { Some(args) };
+
+        match (command, arg) {
+            ("set", Some(args)) => {
+                let mut parts = args.splitn(2, char::is_whitespace);
+                let name = parts.next().unwrap();
+                let value = parts
+                    .next()
+                    .map(|s| s.trim())
+                    .ok_or_else(|| err_msg(format!("missing value for '{}'", name)))?;
+
+                self.change(|settings| settings.set(name, Some(value)))
+            }
+
+            ("unset", Some(name)) => self.change(|settings| settings.set(name, None)),
+            ("repertoire", Some(name)) => {
+                self.change(|settings| settings.set("repertoire", Some(name)))
+            }
+            ("seed", seed) => self.change(|settings| settings.set("seed", seed)),
+            ("transform", None) => Ok(Reply::Message(format!(
+                "transform: [{}]",
+                self.settings
+                    .transform
+                    .clone()
+                    .unwrap_or_default()
+                    .join(", ")
+            ))),
+            ("transform", Some(args)) => {
+                let mut transform = self.settings.transform.clone().unwrap_or_default();
+                if let Some(name) = args.strip_prefix('+') {
+                    transform.push(name.trim().to_owned());
+                } else if let Some(name) = args.strip_prefix('-') {
+                    let name = name.trim();
+                    let index = transform
+                        .iter()
+                        .rposition(|t| t == name)
+                        .ok_or_else(|| err_msg(format!("'{}' is not being used", name)))?;
+                    transform.remove(index);
+                } else {
+                    transform = args
+                        .split(',')
+                        .map(|s| s.trim().to_owned())
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                }
+
+                self.change(|settings| {
+                    settings.transform = Some(transform);
+                    Ok(())
+                })
+            }
+
+            ("preset", Some(name)) => {
+                let preset = self.config.settings(Some(name))?;
+                self.change(|settings| {
+                    settings.merge(&preset);
+                    Ok(())
+                })
+            }
+
+            ("strip", None) => {
+                self.strip = !self.strip;
+                Ok(Reply::Message(if self.strip {
+                    "Stripping lines.".to_owned()
+                } else {
+                    "Joinerating lines.".to_owned()
+                }))
+            }
+
+            ("show", None) => Ok(Reply::Message(
+                self.settings.to_toml()?.trim_end().to_owned(),
+            )),
+            ("help", None) => Ok(Reply::Message(help())),
+            ("quit", None) | ("q", None) | ("exit", None) => Ok(Reply::Quit),
+            _ => Err(err_msg(format!(
+                "unknown command or missing argument ':{}' (see :help)",
+                command
+            ))),
+        }
+    }
+
+    /// Reads lines with line editing and history until the end of the input.
+    #[cfg(feature = "repl")]
+    pub fn run_editor(&mut self) -> Result<(), Error> {
+        let mut editor = DefaultEditor::new()?;
+        loop {
+            let line = match editor.readline(PROMPT) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => return Ok(()),
+                Err(error) => return Err(error.into()),
+            };
+
+            if !line.trim().is_empty() {
+                editor.add_history_entry(line.as_str())?;
+            }
+
+            if !self.reply(&line, &mut io::stdout(), &mut io::stderr())? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads lines without line editing until the end of the input.
+    ///
+    /// ## Arguments
+    /// - `input`    - The lines to handle.
+    /// - `output`   - Where processed lines are written.
+    /// - `messages` - Where messages and errors are written.
+    pub fn run_plain<R: BufRead, W: Write, M: Write>(
+        &mut self,
+        input: R,
+        output: &mut W,
+        messages: &mut M,
+    ) -> Result<(), Error> {
+        for line in input.lines() {
+            if !self.reply(&line?, output, messages)? {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles a line, and shows its reply.
+    /// Processed lines are written to the output, and messages and errors are written separately.
+    ///
+    /// ## Returns
+    /// False if the user quit.
+    fn reply<W: Write, M: Write>(
+        &mut self,
+        line: &str,
+        output: &mut W,
+        messages: &mut M,
+    ) -> Result<bool, Error> {
+        match self.handle(line.trim_end_matches('\r')) {
+            Ok(Reply::Output(text)) => {
+                writeln!(output, "{}", text)?;
+                output.flush()?;
+            }
+            Ok(Reply::Message(message)) => writeln!(messages, "{}", message)?,
+            Ok(Reply::Empty) => {}
+            Ok(Reply::Quit) => return Ok(false),
+            Err(error) => writeln!(messages, "error: {}", error)?,
+        }
+
+        Ok(true)
+    }
+
+    fn process(&mut self, line: &str) -> Result<String, Error> {
+        if self.strip {
+            let name = self.settings.repertoire.as_deref().unwrap_or("default");
+            let repertoire = self
+                .repertoires
+                .get(name)
+                .ok_or_else(|| err_msg(format!("unknown repertoire '{}'", name)))?;
+
+            let options = StripOptions {
+                mode: StripMode::Repertoire(repertoire),
+                preserve_diacritics: false,
+            };
+
+            return Ok(strip::strip(line, &options).text);
+        }
+
+        let state = self.state.as_mut().unwrap();
+        let transformers = &mut state.transformers;
+        let transformed = state.format.map_visible(line, |text| {
+            transformers
+                .iter_mut()
+                .try_fold(text, |text, t| t.transform(text))
+        })?;
+
+        Ok(state.joinerator.process(&transformed))
+    }
+
+    /// Changes the settings, keeping the old settings if the new settings are invalid.
+    fn change<F>(&mut self, f: F) -> Result<Reply, Error>
+    where
+        F: FnOnce(&mut Settings) -> Result<(), crate::config::Error>,
+    {
+        let mut settings = self.settings.clone();
+        f(&mut settings)?;
+        self.apply(settings)?;
+        Ok(Reply::Empty)
+    }
+
+    fn apply(&mut self, settings: Settings) -> Result<(), Error> {
+        let state = State {
+            transformers: settings.transformers()?,
+            format: settings.format()?,
+            joinerator: Joinerator::new(settings.options(self.repertoires)?),
+        };
+
+        self.settings = settings;
+        self.state = Some(state);
+        Ok(())
+    }
+}
+
+fn help() -> String {
+    let mut help = String::from("Lines are joinerated. Lines starting with ':' are commands:");
+    for (command, description) in COMMANDS {
+        help.push_str(&format!("\n  {:32} {}", command, description));
+    }
+
+    help.push_str(&format!("\nTransformers: {}", transform::NAMES.join(", ")));
+    help
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Runs a script, and returns the processed lines and the messages.
+    fn run(config: Config, script: &str) -> (String, String) {
+        let repertoires = RepertoireSet::with_builtin();
+        let settings = Settings {
+            seed: Some(1),
+            limit: Some(0),
+            ..Settings::default()
+        };
+
+        let mut output = vec![];
+        let mut messages = vec![];
+        Repl::new(&repertoires, config, settings)
+            .unwrap()
+            .run_plain(Cursor::new(script), &mut output, &mut messages)
+            .unwrap();
+
+        (
+            String::from_utf8(output).unwrap(),
+            String::from_utf8(messages).unwrap(),
+        )
+    }
+
+    #[test]
+    fn text_lines() {
+        let (output, messages) = run(Config::default(), "hello\n::colon\r\n\nworld");
+        assert_eq!(output, "hello\n:colon\n\nworld\n");
+        assert_eq!(messages, "");
+    }
+
+    #[test]
+    fn set_and_show() {
+        let (output, messages) = run(
+            Config::default(),
+            ":set transform uppercase, uwuize\n:transform -uwuize\n:show\nhello\n:unset transform\nhello\n",
+        );
+
+        assert_eq!(output, "HELLO\nhello\n");
+        assert_eq!(
+            messages,
+            "limit = 0\nseed = 1\ntransform = [\"uppercase\"]\n"
+        );
+    }
+
+    #[test]
+    fn set_follows_merge_rules() {
+        let (_, messages) = run(
+            Config::default(),
+            ":set generators above:1:1\n:set above.stacking 2\n:set limit-preset discord\n:show",
+        );
+
+        assert_eq!(
+            messages,
+            "limit-preset = \"discord\"\nseed = 1\n[generator.above]\nstacking = 2\n"
+        );
+    }
+
+    #[test]
+    fn presets() {
+        let config = Config::from_toml("[preset.loud]\ntransform = [\"uppercase\"]").unwrap();
+        let (output, messages) = run(config, ":preset loud\nhello\n:preset quiet\nhello");
+        assert_eq!(output, "HELLO\nHELLO\n");
+        assert!(messages.starts_with("error: "), "{}", messages);
+    }
+
+    #[test]
+    fn strip() {
+        let (output, messages) = run(Config::default(), ":strip\nh\u{0300}i\n:strip\nh\u{0300}i");
+        assert_eq!(output, "hi\nh\u{0300}i\n");
+        assert_eq!(messages, "Stripping lines.\nJoinerating lines.\n");
+    }
+
+    #[test]
+    fn bad_commands_keep_the_settings() {
+        let (output, messages) = run(
+            Config::default(),
+            ":frobnicate\n:set limit\n:set limit lots\n:set density sideways\n:transform -uwuize\nhello",
+        );
+
+        assert_eq!(output, "hello\n");
+        assert_eq!(messages.lines().count(), 5, "{}", messages);
+        assert!(messages.lines().all(|l| l.starts_with("error: ")));
+        assert!(messages.contains("unknown command or missing argument ':frobnicate'"));
+    }
+
+    #[test]
+    fn quit() {
+        let (output, _) = run(Config::default(), "one\n:quit\ntwo");
+        assert_eq!(output, "one\n");
+    }
+}
@@ -0,0 +1,336 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use std::io::Read;
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use failure::{err_msg, Error};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::service::Service;
+// -------------------------------------------------------------------------------------------------
+
+/// The address the server binds to by default.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8419";
+
+/// The largest request body that will be accepted, in bytes.
+pub const MAX_BODY: usize = 1024 * 1024;
+
+/// The number of requests that are handled at the same time by default.
+pub const DEFAULT_WORKERS: usize = 8;
+
+/// A local HTTP server with a JSON API for a `Service`.
+///
+/// | Request            | Body             | Response              |
+/// |--------------------|------------------|-----------------------|
+/// | `POST /process`    | `ProcessRequest` | `ProcessResponse`     |
+/// | `POST /strip`      | `StripRequest`   | `StripResponse`       |
+/// | `GET /repertoires` |                  | `[RepertoireInfo]`    |
+///
+/// Failed requests are answered with `{"error": "..."}` and an error status.
+pub struct Server {
+    server: Arc<tiny_http::Server>,
+    service: Arc<Service>,
+    workers: usize,
+}
+
+/// A failed request.
+struct Failure {
+    status: u16,
+    message: String,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl Server {
+    /// Creates a server listening on an address.
+    ///
+    /// ## Arguments
+    /// - `address` - The address to bind to (e.g. `127.0.0.1:8419`).
+    /// - `service` - The service that handles requests.
+    pub fn bind(address: &str, service: Service) -> Result<Self, Error> {
+        let server = tiny_http::Server::http(address)
+            .map_err(|e| err_msg(format!("failed to bind to '{}': {}", address, e)))?;
+
+        Ok(Server {
+            server: Arc::new(server),
+            service: Arc::new(service),
+            workers: DEFAULT_WORKERS,
+        })
+    }
+
+    /// Sets the number of requests that are handled at the same time.
+    /// Other requests wait until a worker is free.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Gets the address the server is listening on.
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Handles requests until the server fails.
+    /// Requests are handled by a fixed number of worker threads (see `with_workers`).
+    pub fn run(&self) -> Result<(), Error> {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..self.workers {
+            let server = Arc::clone(&self.server);
+            let service = Arc::clone(&self.service);
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let error = loop {
+                    match server.recv() {
+                        Ok(request) => respond(&service, request),
+                        Err(error) => break error,
+                    }
+                };
+
+                let _ = sender.send(error);
+            });
+        }
+
+        drop(sender);
+        match receiver.recv() {
+            Ok(error) => Err(error.into()),
+            Err(_) => Err(err_msg("every server worker stopped unexpectedly")),
+        }
+    }
+}
+
+impl Failure {
+    fn too_large() -> Self {
+        Failure {
+            status: 413,
+            message: format!("request body is larger than {} bytes", MAX_BODY),
+        }
+    }
+
+    fn bad_request<E: ToString>(error: E) -> Self {
+        Failure {
+            status: 400,
+            message: error.to_string(),
+        }
+    }
+}
+
+fn respond(service: &Service, mut request: Request) {
+    let response = match handle(service, &mut request) {
+        Ok(body) => json_response(200, body),
+        Err(failure) => json_response(
+            failure.status,
+            serde_json::json!({ "error": failure.message }).to_string(),
+        ),
+    };
+
+    // A client that disconnects early is not a server failure.
+    let _ = request.respond(response);
+}
+
+fn handle(service: &Service, request: &mut Request) -> Result<String, Failure> {
+    let path = request.url().split('?').next().unwrap_or("").to_owned();
+    match (request.method(), path.as_str()) {
+        (Method::Post, "/process") => {
+            let body = read_json(request)?;
+            to_json(&service.process(&body).map_err(Failure::bad_request)?)
+        }
+
+        (Method::Post, "/strip") => {
+            let body = read_json(request)?;
+            to_json(&service.strip(&body).map_err(Failure::bad_request)?)
+        }
+
+        (Method::Get, "/repertoires") => to_json(&service.repertoires()),
+
+        (_, "/process") | (_, "/strip") | (_, "/repertoires") => Err(Failure {
+            status: 405,
+            message: format!("method {} is not allowed for '{}'", request.method(), path),
+        }),
+
+        _ => Err(Failure {
+            status: 404,
+            message: format!("unknown path '{}'", path),
+        }),
+    }
+}
+
+fn read_json<T: DeserializeOwned>(request: &mut Request) -> Result<T, Failure> {
+    if request.body_length().unwrap_or(0) > MAX_BODY {
+        return Err(Failure::too_large());
+    }
+
+    let mut body = vec![];
+    request
+        .as_reader()
+        .take(MAX_BODY as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(Failure::bad_request)?;
+
+    if body.len() > MAX_BODY {
+        return Err(Failure::too_large());
+    }
+
+    serde_json::from_slice(&body).map_err(Failure::bad_request)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, Failure> {
+    serde_json::to_string(value).map_err(|e| Failure {
+        status: 500,
+        message: e.to_string(),
+    })
+}
+
+fn json_response(status: u16, body: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes(
+        &b"Content-Type"[..],
+        &b"application/json; charset=utf-8"[..],
+    )
+    .unwrap();
+
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header)
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Settings};
+    use crate::repertoire::RepertoireSet;
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    fn start() -> SocketAddr {
+        start_with_workers(DEFAULT_WORKERS)
+    }
+
+    fn start_with_workers(workers: usize) -> SocketAddr {
+        let service = Service::new(
+            RepertoireSet::with_builtin(),
+            Config::default(),
+            Settings::default(),
+        );
+
+        let server = Server::bind("127.0.0.1:0", service)
+            .unwrap()
+            .with_workers(workers);
+        let address = server.address().unwrap();
+        thread::spawn(move || server.run());
+        address
+    }
+
+    fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let status = response[9..12].parse().unwrap();
+        let body = response.split("\r\n\r\n").nth(1).unwrap_or("").to_owned();
+        (status, body)
+    }
+
+    fn json(body: &str) -> serde_json::Value {
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn process() {
+        let address = start();
+        let (status, body) = request(
+            address,
+            "POST",
+            "/process",
+            r#"{"text": "Hello", "settings": {"transform": ["uppercase"], "limit": 0}}"#,
+        );
+
+        assert_eq!(status, 200);
+        assert_eq!(json(&body)["text"], "HELLO");
+    }
+
+    #[test]
+    fn strip() {
+        let address = start();
+        let (status, body) = request(
+            address,
+            "POST",
+            "/strip",
+            "{\"text\": \"a\u{0300}b\", \"all\": true}",
+        );
+
+        assert_eq!(status, 200);
+        assert_eq!(json(&body)["text"], "ab");
+        assert_eq!(json(&body)["removed"], 1);
+    }
+
+    #[test]
+    fn repertoires() {
+        let (status, body) = request(start(), "GET", "/repertoires", "");
+        assert_eq!(status, 200);
+        assert!(json(&body)
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|r| r["name"] == "default"));
+    }
+
+    #[test]
+    fn errors() {
+        let address = start();
+        let bad_request = |body: &str| request(address, "POST", "/process", body).0;
+        assert_eq!(bad_request("not json"), 400);
+        assert_eq!(bad_request(r#"{"unknown": 1}"#), 400);
+        assert_eq!(
+            bad_request(r#"{"text": "a", "settings": {"generators": ["above:100%:17"]}}"#),
+            400
+        );
+        assert_eq!(
+            bad_request(r#"{"text": "a", "settings": {"limit": 99999999}}"#),
+            400
+        );
+
+        let (status, body) = request(address, "GET", "/process", "");
+        assert_eq!(status, 405);
+        assert!(json(&body)["error"].is_string());
+        assert_eq!(request(address, "GET", "/missing", "").0, 404);
+
+        let large = format!("{{\"text\": \"{}\"}}", "a".repeat(MAX_BODY));
+        assert_eq!(request(address, "POST", "/process", &large).0, 413);
+    }
+
+    #[test]
+    fn more_requests_than_workers() {
+        let address = start_with_workers(2);
+        let clients: Vec<_> = (0..16)
+            .map(|i| {
+                thread::spawn(move || {
+                    let body = format!(r#"{{"text": "{}", "settings": {{"limit": 0}}}}"#, i);
+                    request(address, "POST", "/process", &body)
+                })
+            })
+            .collect();
+
+        for (i, client) in clients.into_iter().enumerate() {
+            let (status, body) = client.join().unwrap();
+            assert_eq!(status, 200);
+            assert_eq!(json(&body)["text"], i.to_string());
+        }
+    }
+}
@@ -0,0 +1,371 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use failure::{format_err, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, Settings};
+use crate::joinerator::{GeneratorFrequency, Joinerator, Options};
+use crate::repertoire::RepertoireSet;
+use crate::strip::{self, StripMode, StripOptions};
+// -------------------------------------------------------------------------------------------------
+
+/// The longest text that a request can contain, in bytes.
+pub const MAX_TEXT: usize = 64 * 1024;
+
+/// The largest number of generators that a request can use.
+pub const MAX_GENERATORS: usize = 16;
+
+/// The largest stacking size that a request can use.
+pub const MAX_STACKING: usize = 16;
+
+/// The largest fixed generator frequency that a request can use.
+pub const MAX_FREQUENCY: usize = MAX_TEXT;
+
+/// The largest length limit that a request can use.
+pub const MAX_LIMIT: usize = 16 * MAX_TEXT;
+
+/// A long-running service that handles requests with one loaded set of repertoires.
+/// This is shared by the servers and the interactive mode, which only translate requests and
+/// responses.
+///
+/// Requests that exceed any of the `MAX_*` constants are rejected, so a single request can't make
+/// the service produce an unreasonable amount of output.
+pub struct Service {
+    repertoires: RepertoireSet,
+    config: Config,
+    defaults: Settings,
+}
+
+/// A request to joinerate text.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProcessRequest {
+    /// The text to joinerate.
+    pub text: String,
+
+    /// The preset applied before the request settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+
+    /// The settings for this request, which are merged on top of the service defaults.
+    pub settings: Settings,
+}
+
+/// A request to remove combining marks from text.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct StripRequest {
+    /// The text to strip.
+    pub text: String,
+
+    /// The repertoire to remove. This defaults to the repertoire of the service defaults.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repertoire: Option<String>,
+
+    /// Removes every nonspacing mark instead of a repertoire.
+    pub all: bool,
+
+    /// Preserves canonical diacritics (e.g. `é`).
+    pub preserve_diacritics: bool,
+}
+
+/// The result of a `ProcessRequest`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessResponse {
+    pub text: String,
+}
+
+/// The result of a `StripRequest`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StripResponse {
+    pub text: String,
+
+    /// The total number of marks removed.
+    pub removed: usize,
+}
+
+/// A description of a loaded repertoire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepertoireInfo {
+    pub name: String,
+    pub description: String,
+    pub glyphs: usize,
+
+    /// The file the repertoire was loaded from, or `None` if it is built in.
+    pub source: Option<String>,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl Service {
+    /// Creates a service.
+    ///
+    /// ## Arguments
+    /// - `repertoires` - The repertoires available to requests.
+    /// - `config`      - The configuration, which provides the presets available to requests.
+    /// - `defaults`    - The settings that every request is merged on top of.
+    pub fn new(repertoires: RepertoireSet, config: Config, defaults: Settings) -> Self {
+        Service {
+            repertoires,
+            config,
+            defaults,
+        }
+    }
+
+    /// Gets the settings that every request is merged on top of.
+    pub fn defaults(&self) -> &Settings {
+        &self.defaults
+    }
+
+    /// Joinerates text.
+    pub fn process(&self, request: &ProcessRequest) -> Result<ProcessResponse, Error> {
+        check_text(&request.text)?;
+
+        let mut settings = self.defaults.clone();
+        if let Some(ref preset) = request.preset {
+            settings.merge(&self.config.settings(Some(preset))?);
+        }
+
+        settings.merge(&request.settings);
+
+        let mut transformers = settings.transformers()?;
+        let format = settings.format()?;
+        let options = settings.options(&self.repertoires)?;
+        check_options(&options)?;
+
+        let mut joinerator = Joinerator::new(options);
+        let transformed = format.map_visible(&request.text, |text| {
+            transformers
+                .iter_mut()
+                .try_fold(text, |text, t| t.transform(text))
+        })?;
+
+        Ok(ProcessResponse {
+            text: joinerator.process(&transformed),
+        })
+    }
+
+    /// Removes combining marks from text.
+    pub fn strip(&self, request: &StripRequest) -> Result<StripResponse, Error> {
+        check_text(&request.text)?;
+
+        let mode = if request.all {
+            StripMode::AllMarks
+        } else {
+            let name = request
+                .repertoire
+                .as_deref()
+                .or(self.defaults.repertoire.as_deref())
+                .unwrap_or("default");
+
+            StripMode::Repertoire(self.repertoires.get(name).ok_or_else(|| {
+                format_err!(
+                    "unknown repertoire '{}' (available: {})",
+                    name,
+                    self.repertoires.names().join(", ")
+                )
+            })?)
+        };
+
+        let stripped = strip::strip(
+            &request.text,
+            &StripOptions {
+                mode,
+                preserve_diacritics: request.preserve_diacritics,
+            },
+        );
+
+        Ok(StripResponse {
+            removed: stripped.total_removed(),
+            text: stripped.text,
+        })
+    }
+
+    /// Lists the loaded repertoires.
+    pub fn repertoires(&self) -> Vec<RepertoireInfo> {
+        self.repertoires
+            .names()
+            .into_iter()
+            .map(|name| {
+                let repertoire = self.repertoires.get(name).unwrap();
+                RepertoireInfo {
+                    name: repertoire.name.clone(),
+                    description: repertoire.description.clone(),
+                    glyphs: repertoire.glyphs.len(),
+                    source: self
+                        .repertoires
+                        .source(name)
+                        .map(|p| p.to_string_lossy().into_owned()),
+                }
+            })
+            .collect()
+    }
+}
+
+fn check_text(text: &str) -> Result<(), Error> {
+    if text.len() > MAX_TEXT {
+        return Err(format_err!("text is longer than {} bytes", MAX_TEXT));
+    }
+
+    Ok(())
+}
+
+fn check_options(options: &Options) -> Result<(), Error> {
+    if options.generator.len() > MAX_GENERATORS {
+        return Err(format_err!(
+            "too many generators (the maximum is {})",
+            MAX_GENERATORS
+        ));
+    }
+
+    for generator in options.generator.iter() {
+        if generator.stacking > MAX_STACKING {
+            return Err(format_err!(
+                "stacking {} is larger than {}",
+                generator.stacking,
+                MAX_STACKING
+            ));
+        }
+
+        if let GeneratorFrequency::Fixed(frequency) = generator.frequency {
+            if frequency > MAX_FREQUENCY {
+                return Err(format_err!(
+                    "frequency {} is larger than {}",
+                    frequency,
+                    MAX_FREQUENCY
+                ));
+            }
+        }
+    }
+
+    match options.limit {
+        Some(limit) if limit > MAX_LIMIT => {
+            Err(format_err!("limit {} is larger than {}", limit, MAX_LIMIT))
+        }
+        _ => Ok(()),
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> Service {
+        let mut config = Config::default();
+        config.preset.insert(
+            "plain".to_owned(),
+            Settings {
+                transform: Some(vec!["uppercase".to_owned()]),
+                limit: Some(0),
+                ..Settings::default()
+            },
+        );
+
+        Service::new(RepertoireSet::with_builtin(), config, Settings::default())
+    }
+
+    fn process(settings: Settings) -> Result<ProcessResponse, Error> {
+        service().process(&ProcessRequest {
+            text: "Hello".to_owned(),
+            preset: None,
+            settings,
+        })
+    }
+
+    fn generators(specs: &[&str]) -> Settings {
+        Settings {
+            generators: Some(specs.iter().map(|s| (*s).to_owned()).collect()),
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn process_is_seeded() {
+        let settings = Settings {
+            seed: Some(1),
+            ..generators(&["above:100%:2"])
+        };
+
+        let first = process(settings.clone()).unwrap().text;
+        assert_eq!(process(settings).unwrap().text, first);
+        assert_ne!(first, "Hello");
+    }
+
+    #[test]
+    fn process_applies_presets() {
+        let response = service()
+            .process(&ProcessRequest {
+                text: "Hello".to_owned(),
+                preset: Some("plain".to_owned()),
+                settings: Settings::default(),
+            })
+            .unwrap();
+
+        assert_eq!(response.text, "HELLO");
+        assert!(service()
+            .process(&ProcessRequest {
+                preset: Some("missing".to_owned()),
+                ..ProcessRequest::default()
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn process_rejects_values_over_the_caps() {
+        let stacking = format!("above:100%:{}", MAX_STACKING + 1);
+        let frequency = format!("above:{}:1", MAX_FREQUENCY + 1);
+        assert!(process(generators(&["above:100%:16"])).is_ok());
+        assert!(process(generators(&[&stacking])).is_err());
+        assert!(process(generators(&[&frequency])).is_err());
+        assert!(process(generators(&["above:1:1"; MAX_GENERATORS + 1])).is_err());
+        assert!(process(Settings {
+            limit: Some(MAX_LIMIT + 1),
+            ..Settings::default()
+        })
+        .is_err());
+
+        let text = "a".repeat(MAX_TEXT + 1);
+        assert!(service()
+            .process(&ProcessRequest {
+                text: text.clone(),
+                ..ProcessRequest::default()
+            })
+            .is_err());
+        assert!(service()
+            .strip(&StripRequest {
+                text,
+                ..StripRequest::default()
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn strip() {
+        let response = service()
+            .strip(&StripRequest {
+                text: "a\u{0300}b\u{20DD}".to_owned(),
+                all: true,
+                ..StripRequest::default()
+            })
+            .unwrap();
+
+        assert_eq!(response.text, "ab");
+        assert_eq!(response.removed, 2);
+        assert!(service()
+            .strip(&StripRequest {
+                repertoire: Some("missing".to_owned()),
+                ..StripRequest::default()
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn repertoires() {
+        let repertoires = service().repertoires();
+        assert!(repertoires.iter().any(|r| r.name == "default"));
+        assert!(repertoires.iter().all(|r| r.source.is_none()));
+    }
+}
@@ -0,0 +1,169 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use std::collections::HashSet;
+
+use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::repertoire::Repertoire;
+// -------------------------------------------------------------------------------------------------
+
+lazy_static! {
+    static ref REGEX_MARK: Regex = Regex::new("^[\\p{Mn}\\p{Me}]$").unwrap();
+}
+
+/// The placeholder used in reports for marks that were not preceded by any character.
+pub const NO_BASE: char = '\u{25CC}';
+
+/// Which combining marks will be removed by `strip`.
+pub enum StripMode<'a> {
+    /// Removes only the combining glyphs found in a repertoire.
+    Repertoire(&'a Repertoire),
+
+    /// Removes every Unicode nonspacing or enclosing mark.
+    AllMarks,
+}
+
+/// The options used for `strip`.
+pub struct StripOptions<'a> {
+    /// Which combining marks will be removed.
+    pub mode: StripMode<'a>,
+
+    /// Preserves canonical diacritics (e.g. `é`) by applying NFC composition before stripping.
+    pub preserve_diacritics: bool,
+}
+
+/// The result of `strip`.
+pub struct Stripped {
+    /// The text without combining marks.
+    pub text: String,
+
+    /// The number of marks removed after each character of the stripped text, in order.
+    /// Marks that appear before the first character are reported against `NO_BASE`.
+    pub removed: Vec<(char, usize)>,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl Stripped {
+    /// Gets the total number of marks that were removed.
+    pub fn total_removed(&self) -> usize {
+        self.removed.iter().map(|(_, n)| n).sum()
+    }
+}
+
+/// Removes combining marks from a string, reversing the work of `Joinerator::process`.
+///
+/// ## Arguments
+/// - `input`   - The input string.
+/// - `options` - The options describing which marks to remove.
+///
+/// ## Returns
+/// The stripped text, and a report of how many marks were removed from each character.
+pub fn strip(input: &str, options: &StripOptions) -> Stripped {
+    let known: HashSet<char> = match options.mode {
+        StripMode::Repertoire(repertoire) => {
+            repertoire.glyphs.iter().map(|g| g.codepoint).collect()
+        }
+        StripMode::AllMarks => HashSet::new(),
+    };
+
+    let is_removable = |c: char| match options.mode {
+        StripMode::Repertoire(_) => known.contains(&c),
+        StripMode::AllMarks => {
+            let mut buffer = [0; 4];
+            REGEX_MARK.is_match(c.encode_utf8(&mut buffer))
+        }
+    };
+
+    let composed: String = if options.preserve_diacritics {
+        input.nfc().collect()
+    } else {
+        input.to_owned()
+    };
+
+    let mut text = String::with_capacity(composed.len());
+    let mut removed: Vec<(char, usize)> = Vec::new();
+    for c in composed.chars() {
+        if !is_removable(c) {
+            text.push(c);
+            removed.push((c, 0));
+            continue;
+        }
+
+        if removed.is_empty() {
+            removed.push((NO_BASE, 0));
+        }
+
+        removed.last_mut().unwrap().1 += 1;
+    }
+
+    Stripped { text, removed }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::joinerator::{GeneratorFrequency, Joinerator, Options};
+    use crate::repertoire::{self, GlyphPosition};
+
+    fn options(mode: StripMode, preserve_diacritics: bool) -> StripOptions {
+        StripOptions {
+            mode,
+            preserve_diacritics,
+        }
+    }
+
+    #[test]
+    fn strip_reverses_process() {
+        let repertoire = repertoire::get("default").unwrap();
+        let joinerator_options = Options::builder(repertoire)
+            .generator(GlyphPosition::ABOVE, GeneratorFrequency::Percentage(1.0), 3)
+            .generator(GlyphPosition::BELOW, GeneratorFrequency::Percentage(1.0), 3)
+            .seed(7)
+            .build();
+
+        let input = "Hello, world!";
+        let output = Joinerator::new(joinerator_options).process(input);
+        let stripped = strip(&output, &options(StripMode::Repertoire(repertoire), false));
+
+        assert_eq!(stripped.text, input);
+        assert_eq!(
+            stripped.total_removed(),
+            output.chars().count() - input.len()
+        );
+    }
+
+    #[test]
+    fn strip_all_marks() {
+        let stripped = strip(
+            "a\u{0300}\u{0301}b\u{20DD}c",
+            &options(StripMode::AllMarks, false),
+        );
+        assert_eq!(stripped.text, "abc");
+        assert_eq!(stripped.removed, vec![('a', 2), ('b', 1), ('c', 0)]);
+    }
+
+    #[test]
+    fn strip_reports_marks_without_base() {
+        let stripped = strip("\u{0300}a", &options(StripMode::AllMarks, false));
+        assert_eq!(stripped.text, "a");
+        assert_eq!(stripped.removed, vec![(NO_BASE, 1), ('a', 0)]);
+    }
+
+    #[test]
+    fn strip_preserves_diacritics() {
+        let input = "cafe\u{0301}\u{0302}";
+        assert_eq!(
+            strip(input, &options(StripMode::AllMarks, false)).text,
+            "cafe"
+        );
+        assert_eq!(
+            strip(input, &options(StripMode::AllMarks, true)).text,
+            "caf\u{00E9}"
+        );
+    }
+}
@@ -0,0 +1,349 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use std::char;
+
+use failure::Error;
+
+use crate::transform::Transformer;
+// -------------------------------------------------------------------------------------------------
+
+/// The suffix of the name of a transformer that reverses an alphabet.
+pub const REVERSE_SUFFIX: &str = "-reverse";
+
+/// A mapping from plain characters to stylized lookalikes.
+///
+/// Characters are looked up in `pairs` first, and then in `ranges`.
+/// When reversing, the first entry that produces a stylized character wins, so alphabets that
+/// map several characters to one lookalike (e.g. both cases of a letter) reverse to the first.
+pub struct Alphabet {
+    pub name: &'static str,
+
+    /// Ranges of characters `(first, last, codepoint of first)` that map to consecutive
+    /// codepoints.
+    pub ranges: &'static [(char, char, u32)],
+
+    /// Individual characters `(plain, stylized)`.
+    pub pairs: &'static [(char, char)],
+}
+
+/// A transformer that converts characters to (or from) the lookalikes of an alphabet.
+/// Characters without a lookalike are left unchanged.
+pub struct TransformAlphabet {
+    alphabet: &'static Alphabet,
+    reverse: bool,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+/// Fullwidth forms (e.g. `Ａ`), for all printable ASCII.
+/// The space becomes an ideographic space (U+3000).
+pub const FULLWIDTH: Alphabet = Alphabet {
+    name: "fullwidth",
+    ranges: &[('!', '~', 0xFF01)],
+    pairs: &[(' ', '\u{3000}')],
+};
+
+/// Small capitals (e.g. `ᴀ`), for lowercase letters.
+/// There is no small capital X, so `x` is left unchanged.
+pub const SMALL_CAPS: Alphabet = Alphabet {
+    name: "smallcaps",
+    ranges: &[],
+    pairs: &[
+        ('a', '\u{1D00}'),
+        ('b', '\u{0299}'),
+        ('c', '\u{1D04}'),
+        ('d', '\u{1D05}'),
+        ('e', '\u{1D07}'),
+        ('f', '\u{A730}'),
+        ('g', '\u{0262}'),
+        ('h', '\u{029C}'),
+        ('i', '\u{026A}'),
+        ('j', '\u{1D0A}'),
+        ('k', '\u{1D0B}'),
+        ('l', '\u{029F}'),
+        ('m', '\u{1D0D}'),
+        ('n', '\u{0274}'),
+        ('o', '\u{1D0F}'),
+        ('p', '\u{1D18}'),
+        ('q', '\u{A7AF}'),
+        ('r', '\u{0280}'),
+        ('s', '\u{A731}'),
+        ('t', '\u{1D1B}'),
+        ('u', '\u{1D1C}'),
+        ('v', '\u{1D20}'),
+        ('w', '\u{1D21}'),
+        ('y', '\u{028F}'),
+        ('z', '\u{1D22}'),
+    ],
+};
+
+/// Superscript (modifier) letters, digits, and `+-=()` (e.g. `ᵃ`, `²`).
+/// There are no superscript versions of `q` or the uppercase `C F Q S X Y Z`.
+pub const SUPERSCRIPT: Alphabet = Alphabet {
+    name: "superscript",
+    ranges: &[('4', '9', 0x2074)],
+    pairs: &[
+        ('0', '\u{2070}'),
+        ('1', '\u{00B9}'),
+        ('2', '\u{00B2}'),
+        ('3', '\u{00B3}'),
+        ('+', '\u{207A}'),
+        ('-', '\u{207B}'),
+        ('=', '\u{207C}'),
+        ('(', '\u{207D}'),
+        (')', '\u{207E}'),
+        ('a', '\u{1D43}'),
+        ('b', '\u{1D47}'),
+        ('c', '\u{1D9C}'),
+        ('d', '\u{1D48}'),
+        ('e', '\u{1D49}'),
+        ('f', '\u{1DA0}'),
+        ('g', '\u{1D4D}'),
+        ('h', '\u{02B0}'),
+        ('i', '\u{2071}'),
+        ('j', '\u{02B2}'),
+        ('k', '\u{1D4F}'),
+        ('l', '\u{02E1}'),
+        ('m', '\u{1D50}'),
+        ('n', '\u{207F}'),
+        ('o', '\u{1D52}'),
+        ('p', '\u{1D56}'),
+        ('r', '\u{02B3}'),
+        ('s', '\u{02E2}'),
+        ('t', '\u{1D57}'),
+        ('u', '\u{1D58}'),
+        ('v', '\u{1D5B}'),
+        ('w', '\u{02B7}'),
+        ('x', '\u{02E3}'),
+        ('y', '\u{02B8}'),
+        ('z', '\u{1DBB}'),
+        ('A', '\u{1D2C}'),
+        ('B', '\u{1D2E}'),
+        ('D', '\u{1D30}'),
+        ('E', '\u{1D31}'),
+        ('G', '\u{1D33}'),
+        ('H', '\u{1D34}'),
+        ('I', '\u{1D35}'),
+        ('J', '\u{1D36}'),
+        ('K', '\u{1D37}'),
+        ('L', '\u{1D38}'),
+        ('M', '\u{1D39}'),
+        ('N', '\u{1D3A}'),
+        ('O', '\u{1D3C}'),
+        ('P', '\u{1D3E}'),
+        ('R', '\u{1D3F}'),
+        ('T', '\u{1D40}'),
+        ('U', '\u{1D41}'),
+        ('V', '\u{2C7D}'),
+        ('W', '\u{1D42}'),
+    ],
+};
+
+/// Subscript letters, digits, and `+-=()` (e.g. `ₐ`, `₂`).
+/// Only `a e h i j k l m n o p r s t u v x` have subscript versions, and only in lowercase.
+pub const SUBSCRIPT: Alphabet = Alphabet {
+    name: "subscript",
+    ranges: &[('0', '9', 0x2080)],
+    pairs: &[
+        ('+', '\u{208A}'),
+        ('-', '\u{208B}'),
+        ('=', '\u{208C}'),
+        ('(', '\u{208D}'),
+        (')', '\u{208E}'),
+        ('a', '\u{2090}'),
+        ('e', '\u{2091}'),
+        ('o', '\u{2092}'),
+        ('x', '\u{2093}'),
+        ('h', '\u{2095}'),
+        ('k', '\u{2096}'),
+        ('l', '\u{2097}'),
+        ('m', '\u{2098}'),
+        ('n', '\u{2099}'),
+        ('p', '\u{209A}'),
+        ('s', '\u{209B}'),
+        ('t', '\u{209C}'),
+        ('i', '\u{1D62}'),
+        ('r', '\u{1D63}'),
+        ('u', '\u{1D64}'),
+        ('v', '\u{1D65}'),
+        ('j', '\u{2C7C}'),
+    ],
+};
+
+/// Circled letters and digits (e.g. `ⓐ`, `Ⓐ`, `①`).
+pub const CIRCLED: Alphabet = Alphabet {
+    name: "circled",
+    ranges: &[('A', 'Z', 0x24B6), ('a', 'z', 0x24D0), ('1', '9', 0x2460)],
+    pairs: &[('0', '\u{24EA}')],
+};
+
+/// Negative squared letters (e.g. `🅰`).
+/// There is only one case, so reversing gives uppercase letters.
+pub const NEGATIVE_SQUARED: Alphabet = Alphabet {
+    name: "negative-squared",
+    ranges: &[('A', 'Z', 0x1F170), ('a', 'z', 0x1F170)],
+    pairs: &[],
+};
+
+/// Regional indicator symbols (e.g. `🇦`).
+/// There is only one case, so reversing gives uppercase letters. Pairs of regional indicators
+/// that form a country code (e.g. `US`) are displayed as a flag.
+pub const REGIONAL_INDICATOR: Alphabet = Alphabet {
+    name: "regional-indicator",
+    ranges: &[('A', 'Z', 0x1F1E6), ('a', 'z', 0x1F1E6)],
+    pairs: &[],
+};
+
+/// Every alphabet.
+pub const ALPHABETS: &[&Alphabet] = &[
+    &FULLWIDTH,
+    &SMALL_CAPS,
+    &SUPERSCRIPT,
+    &SUBSCRIPT,
+    &CIRCLED,
+    &NEGATIVE_SQUARED,
+    &REGIONAL_INDICATOR,
+];
+
+// -------------------------------------------------------------------------------------------------
+
+impl Alphabet {
+    /// Converts a character to its lookalike.
+    pub fn apply(&self, c: char) -> Option<char> {
+        if let Some(&(_, styled)) = self.pairs.iter().find(|(plain, _)| *plain == c) {
+            return Some(styled);
+        }
+
+        self.ranges
+            .iter()
+            .find(|(first, last, _)| *first <= c && c <= *last)
+            .and_then(|(first, _, start)| char::from_u32(start + (c as u32 - *first as u32)))
+    }
+
+    /// Converts a lookalike back to its character.
+    pub fn reverse(&self, c: char) -> Option<char> {
+        if let Some(&(plain, _)) = self.pairs.iter().find(|(_, styled)| *styled == c) {
+            return Some(plain);
+        }
+
+        self.ranges
+            .iter()
+            .find(|(first, last, start)| {
+                let count = *last as u32 - *first as u32;
+                *start <= c as u32 && c as u32 <= start + count
+            })
+            .and_then(|(first, _, start)| char::from_u32(*first as u32 + (c as u32 - start)))
+    }
+}
+
+impl TransformAlphabet {
+    /// Creates an alphabet transformer.
+    ///
+    /// ## Arguments
+    /// - `alphabet` - The alphabet.
+    /// - `reverse`  - Converts lookalikes back to their characters instead.
+    pub fn new(alphabet: &'static Alphabet, reverse: bool) -> Self {
+        TransformAlphabet { alphabet, reverse }
+    }
+
+    /// Creates an alphabet transformer from its name.
+    /// This is the name of the alphabet, optionally followed by `REVERSE_SUFFIX`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (name, reverse) = name
+            .strip_suffix(REVERSE_SUFFIX)
+            .map_or((name, false), |name| (name, true));
+
+        ALPHABETS
+            .iter()
+            .find(|a| a.name == name)
+            .map(|alphabet| TransformAlphabet::new(alphabet, reverse))
+    }
+}
+
+impl Transformer for TransformAlphabet {
+    fn transform(&mut self, input: String) -> Result<String, Error> {
+        Ok(input
+            .chars()
+            .map(|c| {
+                if self.reverse {
+                    self.alphabet.reverse(c).unwrap_or(c)
+                } else {
+                    self.alphabet.apply(c).unwrap_or(c)
+                }
+            })
+            .collect())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(name: &str, input: &str) -> String {
+        TransformAlphabet::from_name(name)
+            .unwrap()
+            .transform(input.to_owned())
+            .unwrap()
+    }
+
+    #[test]
+    fn from_name() {
+        for alphabet in ALPHABETS {
+            assert!(TransformAlphabet::from_name(alphabet.name).is_some());
+            assert!(
+                TransformAlphabet::from_name(&format!("{}{}", alphabet.name, REVERSE_SUFFIX))
+                    .unwrap()
+                    .reverse
+            );
+        }
+
+        assert!(TransformAlphabet::from_name("-reverse").is_none());
+        assert!(TransformAlphabet::from_name("fullwidth-reverse-reverse").is_none());
+    }
+
+    #[test]
+    fn round_trip() {
+        for alphabet in ALPHABETS {
+            let reverse = format!("{}{}", alphabet.name, REVERSE_SUFFIX);
+            let mapped: String = (' '..='~')
+                .filter(|c| alphabet.apply(*c).is_some())
+                .collect();
+            assert!(!mapped.is_empty(), "{}", alphabet.name);
+
+            let styled = transform(alphabet.name, &mapped);
+            assert!(
+                mapped.chars().zip(styled.chars()).all(|(a, b)| a != b),
+                "{}",
+                alphabet.name
+            );
+
+            // Alphabets with only one case of a letter reverse to the first case they map.
+            let reversed = transform(&reverse, &styled);
+            assert!(reversed.eq_ignore_ascii_case(&mapped), "{}", alphabet.name);
+            assert_eq!(
+                transform(alphabet.name, &reversed),
+                styled,
+                "{}",
+                alphabet.name
+            );
+        }
+    }
+
+    #[test]
+    fn round_trip_keeps_case() {
+        for name in &["fullwidth", "circled", "superscript"] {
+            let input = "Hello World 123";
+            let reverse = format!("{}{}", name, REVERSE_SUFFIX);
+            assert_eq!(transform(&reverse, &transform(name, input)), input);
+        }
+    }
+
+    #[test]
+    fn unmapped_characters_are_unchanged() {
+        assert_eq!(transform("smallcaps", "xé!"), "xé!");
+        assert_eq!(transform("fullwidth-reverse", "abc é"), "abc é");
+    }
+}
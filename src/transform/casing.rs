@@ -2,8 +2,8 @@
 // joinerator | Copyright (C) 2019 eth-p
 // -------------------------------------------------------------------------------------------------
 use failure::Error;
-use rand::rngs::ThreadRng;
-use rand::{thread_rng, Rng};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
 
 use crate::transform::Transformer;
 // -------------------------------------------------------------------------------------------------
@@ -44,13 +44,23 @@ impl Transformer for TransformLowerCase {
 
 /// A transformer that converts everything to a random case.
 pub struct TransformRandomCase {
-    random: ThreadRng,
+    random: ChaChaRng,
 }
 
 impl TransformRandomCase {
     pub fn new() -> Self {
         TransformRandomCase {
-            random: thread_rng(),
+            random: ChaChaRng::from_entropy(),
+        }
+    }
+
+    /// Creates a random case transformer with a seeded random number generator.
+    ///
+    /// ## Arguments
+    /// - `seed` - The seed for the random number generator.
+    pub fn with_seed(seed: u64) -> Self {
+        TransformRandomCase {
+            random: ChaChaRng::seed_from_u64(seed),
         }
     }
 }
@@ -95,3 +105,23 @@ impl Transformer for TransformVowelCase {
             .replace("y", "Y"))
     }
 }
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_case(seed: u64) -> String {
+        TransformRandomCase::with_seed(seed)
+            .transform("the quick brown fox jumps over the lazy dog".to_owned())
+            .unwrap()
+    }
+
+    #[test]
+    fn random_case_is_seeded() {
+        assert_eq!(random_case(1), random_case(1));
+        assert_ne!(random_case(1), random_case(2));
+        assert!(random_case(1).eq_ignore_ascii_case(&random_case(2)));
+    }
+}
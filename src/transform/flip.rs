@@ -0,0 +1,267 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use failure::Error;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::transform::alphabet::Alphabet;
+use crate::transform::Transformer;
+// -------------------------------------------------------------------------------------------------
+
+/// Upside-down (turned) lookalikes (e.g. `ɐ`, `∀`, `¿`).
+/// Characters that look the same when rotated (e.g. `o`, `S`, `8`) are left unchanged, as is `Q`.
+pub const FLIP: Alphabet = Alphabet {
+    name: "flip",
+    ranges: &[],
+    pairs: &[
+        ('a', '\u{0250}'),
+        ('b', 'q'),
+        ('c', '\u{0254}'),
+        ('d', 'p'),
+        ('e', '\u{01DD}'),
+        ('f', '\u{025F}'),
+        ('g', '\u{0183}'),
+        ('h', '\u{0265}'),
+        ('i', '\u{1D09}'),
+        ('j', '\u{027E}'),
+        ('k', '\u{029E}'),
+        ('l', '\u{A781}'),
+        ('m', '\u{026F}'),
+        ('n', 'u'),
+        ('p', 'd'),
+        ('q', 'b'),
+        ('r', '\u{0279}'),
+        ('t', '\u{0287}'),
+        ('u', 'n'),
+        ('v', '\u{028C}'),
+        ('w', '\u{028D}'),
+        ('y', '\u{028E}'),
+        ('A', '\u{2200}'),
+        ('B', '\u{A4ED}'),
+        ('C', '\u{0186}'),
+        ('D', '\u{A4F7}'),
+        ('E', '\u{018E}'),
+        ('F', '\u{2132}'),
+        ('G', '\u{2141}'),
+        ('J', '\u{A4E9}'),
+        ('K', '\u{A4D8}'),
+        ('L', '\u{2142}'),
+        ('M', 'W'),
+        ('P', '\u{0500}'),
+        ('R', '\u{A4E4}'),
+        ('T', '\u{A4D5}'),
+        ('U', '\u{A4F5}'),
+        ('V', '\u{039B}'),
+        ('W', 'M'),
+        ('Y', '\u{2144}'),
+        ('1', '\u{0196}'),
+        ('2', '\u{1105}'),
+        ('3', '\u{0190}'),
+        ('4', '\u{3123}'),
+        ('5', '\u{03DB}'),
+        ('6', '9'),
+        ('7', '\u{3125}'),
+        ('9', '6'),
+        ('.', '\u{02D9}'),
+        (',', '\''),
+        ('\'', ','),
+        ('"', '\u{201E}'),
+        ('!', '\u{00A1}'),
+        ('?', '\u{00BF}'),
+        (';', '\u{061B}'),
+        ('&', '\u{214B}'),
+        ('_', '\u{203E}'),
+        ('(', ')'),
+        (')', '('),
+        ('[', ']'),
+        (']', '['),
+        ('{', '}'),
+        ('}', '{'),
+        ('<', '>'),
+        ('>', '<'),
+    ],
+};
+
+/// Horizontally mirrored lookalikes (e.g. `ɒ`, `Я`, `⸮`).
+/// Characters that look the same when mirrored (e.g. `o`, `A`, `8`) are left unchanged, as are
+/// most digits and the letters without a convincing lookalike (`f h i j k l m n t u v w x y` and
+/// `G H I J M Q T U V W X Y`).
+pub const MIRROR: Alphabet = Alphabet {
+    name: "mirror",
+    ranges: &[],
+    pairs: &[
+        ('a', '\u{0252}'),
+        ('b', 'd'),
+        ('c', '\u{0254}'),
+        ('d', 'b'),
+        ('e', '\u{0258}'),
+        ('g', '\u{03F1}'),
+        ('p', 'q'),
+        ('q', 'p'),
+        ('r', '\u{027F}'),
+        ('s', '\u{A645}'),
+        ('z', '\u{A641}'),
+        ('B', '\u{1660}'),
+        ('C', '\u{0186}'),
+        ('D', '\u{15E1}'),
+        ('E', '\u{018E}'),
+        ('F', '\u{A7FB}'),
+        ('K', '\u{A4D8}'),
+        ('L', '\u{2143}'),
+        ('N', '\u{0418}'),
+        ('P', '\u{A7FC}'),
+        ('R', '\u{042F}'),
+        ('S', '\u{01A7}'),
+        ('Z', '\u{01B8}'),
+        ('3', '\u{0190}'),
+        ('?', '\u{2E2E}'),
+        ('(', ')'),
+        (')', '('),
+        ('[', ']'),
+        (']', '['),
+        ('{', '}'),
+        ('}', '{'),
+        ('<', '>'),
+        ('>', '<'),
+        ('/', '\\'),
+        ('\\', '/'),
+    ],
+};
+
+/// A transformer that turns text upside down, by reversing it and replacing each character with
+/// its upside-down lookalike (see `FLIP`). Combining marks stay with their characters, and
+/// trailing line endings stay at the end.
+pub struct TransformFlip {
+    reverse: bool,
+}
+
+/// A transformer that mirrors text horizontally, by reversing each line and replacing each
+/// character with its mirrored lookalike (see `MIRROR`). Combining marks stay with their
+/// characters.
+pub struct TransformMirror {
+    reverse: bool,
+}
+
+// -------------------------------------------------------------------------------------------------
+
+impl TransformFlip {
+    /// Creates a flip transformer.
+    ///
+    /// ## Arguments
+    /// - `reverse` - Turns flipped text back the right way up instead.
+    pub fn new(reverse: bool) -> Self {
+        TransformFlip { reverse }
+    }
+}
+
+impl Transformer for TransformFlip {
+    fn transform(&mut self, input: String) -> Result<String, Error> {
+        let text = input.trim_end_matches(&['\n', '\r'][..]);
+        let ending = &input[text.len()..];
+        Ok(reverse_graphemes(text, &FLIP, self.reverse) + ending)
+    }
+}
+
+impl TransformMirror {
+    /// Creates a mirror transformer.
+    ///
+    /// ## Arguments
+    /// - `reverse` - Turns mirrored text back instead.
+    pub fn new(reverse: bool) -> Self {
+        TransformMirror { reverse }
+    }
+}
+
+impl Transformer for TransformMirror {
+    fn transform(&mut self, input: String) -> Result<String, Error> {
+        let lines: Vec<String> = input
+            .split('\n')
+            .map(|line| match line.strip_suffix('\r') {
+                Some(line) => reverse_graphemes(line, &MIRROR, self.reverse) + "\r",
+                None => reverse_graphemes(line, &MIRROR, self.reverse),
+            })
+            .collect();
+
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Reverses the order of the grapheme clusters in a string, and replaces their characters with
+/// lookalikes.
+///
+/// ## Arguments
+/// - `input`    - The string.
+/// - `alphabet` - The lookalikes.
+/// - `reverse`  - Replaces lookalikes with their original characters instead.
+fn reverse_graphemes(input: &str, alphabet: &Alphabet, reverse: bool) -> String {
+    let mut output = String::with_capacity(input.len());
+    for grapheme in input.graphemes(true).rev() {
+        output.extend(grapheme.chars().map(|c| {
+            let replacement = if reverse {
+                alphabet.reverse(c)
+            } else {
+                alphabet.apply(c)
+            };
+
+            replacement.unwrap_or(c)
+        }));
+    }
+
+    output
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flip(input: &str, reverse: bool) -> String {
+        TransformFlip::new(reverse)
+            .transform(input.to_owned())
+            .unwrap()
+    }
+
+    fn mirror(input: &str, reverse: bool) -> String {
+        TransformMirror::new(reverse)
+            .transform(input.to_owned())
+            .unwrap()
+    }
+
+    fn printable_ascii() -> String {
+        (' '..='~').collect()
+    }
+
+    #[test]
+    fn flip_round_trip() {
+        let input = printable_ascii();
+        let flipped = flip(&input, false);
+        assert_ne!(flipped, input);
+        assert_eq!(flip(&flipped, true), input);
+    }
+
+    #[test]
+    fn mirror_round_trip() {
+        let input = format!("{}\r\nsecond line\n", printable_ascii());
+        let mirrored = mirror(&input, false);
+        assert_ne!(mirrored, input);
+        assert_eq!(mirror(&mirrored, true), input);
+    }
+
+    #[test]
+    fn flip_keeps_marks_and_line_endings() {
+        assert_eq!(
+            flip("ha\u{0301}\r\n", false),
+            "\u{0250}\u{0301}\u{0265}\r\n"
+        );
+        assert_eq!(
+            flip("hello!\n", false),
+            "\u{00A1}o\u{A781}\u{A781}\u{01DD}\u{0265}\n"
+        );
+    }
+
+    #[test]
+    fn mirror_reverses_each_line() {
+        assert_eq!(mirror("(ab\nxy]\r\n", false), "d\u{0252})\n[yx\r\n");
+    }
+}
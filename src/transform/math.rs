@@ -0,0 +1,207 @@
+// -------------------------------------------------------------------------------------------------
+// joinerator | Copyright (C) 2019 eth-p
+// -------------------------------------------------------------------------------------------------
+use std::char;
+
+use failure::Error;
+
+use crate::transform::Transformer;
+// -------------------------------------------------------------------------------------------------
+
+/// A style from the Mathematical Alphanumeric Symbols block (U+1D400 to U+1D7FF).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MathStyle {
+    Bold,
+    Italic,
+    BoldItalic,
+    Script,
+    Fraktur,
+    DoubleStruck,
+    Sans,
+    Monospace,
+}
+
+/// A transformer that converts Latin letters and digits to a mathematical alphanumeric style.
+/// Characters without a styled version (e.g. digits in italic) are left unchanged.
+pub struct TransformMathAlphabet {
+    style: MathStyle,
+}
+
+/// The first codepoints of a style: uppercase `A`, lowercase `a`, and the digit `0`.
+struct Alphabet {
+    upper: u32,
+    lower: u32,
+    digit: Option<u32>,
+
+    /// Letters that were encoded in the Letterlike Symbols block before the mathematical
+    /// alphanumeric symbols existed. Their places in the mathematical block are unassigned.
+    holes: &'static [(char, char)],
+}
+
+// -------------------------------------------------------------------------------------------------
+
+const ITALIC_HOLES: &[(char, char)] = &[('h', '\u{210E}')];
+
+const SCRIPT_HOLES: &[(char, char)] = &[
+    ('B', '\u{212C}'),
+    ('E', '\u{2130}'),
+    ('F', '\u{2131}'),
+    ('H', '\u{210B}'),
+    ('I', '\u{2110}'),
+    ('L', '\u{2112}'),
+    ('M', '\u{2133}'),
+    ('R', '\u{211B}'),
+    ('e', '\u{212F}'),
+    ('g', '\u{210A}'),
+    ('o', '\u{2134}'),
+];
+
+const FRAKTUR_HOLES: &[(char, char)] = &[
+    ('C', '\u{212D}'),
+    ('H', '\u{210C}'),
+    ('I', '\u{2111}'),
+    ('R', '\u{211C}'),
+    ('Z', '\u{2128}'),
+];
+
+const DOUBLE_STRUCK_HOLES: &[(char, char)] = &[
+    ('C', '\u{2102}'),
+    ('H', '\u{210D}'),
+    ('N', '\u{2115}'),
+    ('P', '\u{2119}'),
+    ('Q', '\u{211A}'),
+    ('R', '\u{211D}'),
+    ('Z', '\u{2124}'),
+];
+
+impl MathStyle {
+    /// Every style.
+    pub const ALL: [MathStyle; 8] = [
+        MathStyle::Bold,
+        MathStyle::Italic,
+        MathStyle::BoldItalic,
+        MathStyle::Script,
+        MathStyle::Fraktur,
+        MathStyle::DoubleStruck,
+        MathStyle::Sans,
+        MathStyle::Monospace,
+    ];
+
+    /// Gets the name of the style, which is also the name of its transformer.
+    pub fn name(self) -> &'static str {
+        match self {
+            MathStyle::Bold => "bold",
+            MathStyle::Italic => "italic",
+            MathStyle::BoldItalic => "bold-italic",
+            MathStyle::Script => "script",
+            MathStyle::Fraktur => "fraktur",
+            MathStyle::DoubleStruck => "double-struck",
+            MathStyle::Sans => "sans",
+            MathStyle::Monospace => "monospace",
+        }
+    }
+
+    /// Gets a style from its name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|s| s.name() == name).cloned()
+    }
+
+    fn alphabet(self) -> Alphabet {
+        let (upper, lower, digit, holes) = match self {
+            MathStyle::Bold => (0x1D400, 0x1D41A, Some(0x1D7CE), &[][..]),
+            MathStyle::Italic => (0x1D434, 0x1D44E, None, ITALIC_HOLES),
+            MathStyle::BoldItalic => (0x1D468, 0x1D482, None, &[][..]),
+            MathStyle::Script => (0x1D49C, 0x1D4B6, None, SCRIPT_HOLES),
+            MathStyle::Fraktur => (0x1D504, 0x1D51E, None, FRAKTUR_HOLES),
+            MathStyle::DoubleStruck => (0x1D538, 0x1D552, Some(0x1D7D8), DOUBLE_STRUCK_HOLES),
+            MathStyle::Sans => (0x1D5A0, 0x1D5BA, Some(0x1D7E2), &[][..]),
+            MathStyle::Monospace => (0x1D670, 0x1D68A, Some(0x1D7F6), &[][..]),
+        };
+
+        Alphabet {
+            upper,
+            lower,
+            digit,
+            holes,
+        }
+    }
+
+    /// Converts a character to the style.
+    ///
+    /// ## Returns
+    /// The styled character, or `None` if it has no styled version.
+    pub fn apply(self, c: char) -> Option<char> {
+        let alphabet = self.alphabet();
+        if let Some(&(_, styled)) = alphabet.holes.iter().find(|(plain, _)| *plain == c) {
+            return Some(styled);
+        }
+
+        let codepoint = match c {
+            'A'..='Z' => alphabet.upper + (c as u32 - 'A' as u32),
+            'a'..='z' => alphabet.lower + (c as u32 - 'a' as u32),
+            '0'..='9' => alphabet.digit? + (c as u32 - '0' as u32),
+            _ => return None,
+        };
+
+        char::from_u32(codepoint)
+    }
+}
+
+impl TransformMathAlphabet {
+    pub fn new(style: MathStyle) -> Self {
+        TransformMathAlphabet { style }
+    }
+}
+
+impl Transformer for TransformMathAlphabet {
+    fn transform(&mut self, input: String) -> Result<String, Error> {
+        Ok(input
+            .chars()
+            .map(|c| self.style.apply(c).unwrap_or(c))
+            .collect())
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_normalization::UnicodeNormalization;
+
+    const PLAIN: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+    #[test]
+    fn from_name() {
+        for style in MathStyle::ALL.iter() {
+            assert_eq!(MathStyle::from_name(style.name()), Some(*style));
+        }
+
+        assert_eq!(MathStyle::from_name("comic-sans"), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        // The styled letters are compatibility variants, so NFKC turns them back into the originals.
+        // Letters that were placed in an unassigned codepoint would not be changed by NFKC.
+        for style in MathStyle::ALL.iter() {
+            let styled = TransformMathAlphabet::new(*style)
+                .transform(PLAIN.to_owned())
+                .unwrap();
+
+            assert!(
+                !styled.chars().any(|c| c.is_ascii_alphabetic()),
+                "{}",
+                style.name()
+            );
+            assert_eq!(styled.nfkc().collect::<String>(), PLAIN, "{}", style.name());
+        }
+    }
+
+    #[test]
+    fn unstyled_characters_are_unchanged() {
+        let mut transformer = TransformMathAlphabet::new(MathStyle::Italic);
+        let styled = transformer.transform("h 1, é!".to_owned()).unwrap();
+        assert_eq!(styled, "\u{210E} 1, é!");
+    }
+}
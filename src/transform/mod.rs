@@ -1,13 +1,121 @@
 // -------------------------------------------------------------------------------------------------
 // joinerator | Copyright (C) 2019 eth-p
 // -------------------------------------------------------------------------------------------------
+pub mod alphabet;
 pub mod casing;
+pub mod flip;
+pub mod math;
 pub mod uwu;
 
 // -------------------------------------------------------------------------------------------------
 use failure::Error;
 // -------------------------------------------------------------------------------------------------
 
+/// A text transformer.
+/// This modifies a string before it is processed by Joinerator.
 pub trait Transformer {
     fn transform(&mut self, input: String) -> Result<String, Error>;
 }
+
+/// The names of every transformer that can be created with `get`.
+pub const NAMES: &[&str] = &[
+    "uppercase",
+    "lowercase",
+    "randomcase",
+    "vowelcase",
+    "upper",
+    "lower",
+    "uwuize",
+    "bold",
+    "italic",
+    "bold-italic",
+    "script",
+    "fraktur",
+    "double-struck",
+    "sans",
+    "monospace",
+    "fullwidth",
+    "fullwidth-reverse",
+    "smallcaps",
+    "smallcaps-reverse",
+    "superscript",
+    "superscript-reverse",
+    "subscript",
+    "subscript-reverse",
+    "circled",
+    "circled-reverse",
+    "negative-squared",
+    "negative-squared-reverse",
+    "regional-indicator",
+    "regional-indicator-reverse",
+    "flip",
+    "flip-reverse",
+    "mirror",
+    "mirror-reverse",
+];
+
+// -------------------------------------------------------------------------------------------------
+
+/// Creates a transformer from its name.
+///
+/// ## Arguments
+/// - `name` - The transformer name (see `NAMES`).
+/// - `seed` - The seed for transformers that use random number generation.
+///
+/// ## Returns
+/// The transformer, or `None` if there is no transformer with the given name.
+pub fn get(name: &str, seed: Option<u64>) -> Option<Box<dyn Transformer>> {
+    Some(match name {
+        "upper" | "uppercase" => Box::new(casing::TransformUpperCase::new()),
+        "lower" | "lowercase" => Box::new(casing::TransformLowerCase::new()),
+        "randomcase" => Box::new(match seed {
+            Some(seed) => casing::TransformRandomCase::with_seed(seed),
+            None => casing::TransformRandomCase::new(),
+        }),
+        "vowelcase" => Box::new(casing::TransformVowelCase::new()),
+        "uwuize" => Box::new(uwu::TransformUwuize::new()),
+        "flip" => Box::new(flip::TransformFlip::new(false)),
+        "flip-reverse" => Box::new(flip::TransformFlip::new(true)),
+        "mirror" => Box::new(flip::TransformMirror::new(false)),
+        "mirror-reverse" => Box::new(flip::TransformMirror::new(true)),
+        name => match math::MathStyle::from_name(name) {
+            Some(style) => Box::new(math::TransformMathAlphabet::new(style)),
+            None => Box::new(alphabet::TransformAlphabet::from_name(name)?),
+        },
+    })
+}
+
+// -------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_creates_every_transformer() {
+        for name in NAMES {
+            assert!(get(name, None).is_some(), "{}", name);
+        }
+
+        assert!(get("sideways", None).is_none());
+    }
+
+    #[test]
+    fn reverse_transformers_undo_their_transformers() {
+        for name in NAMES.iter().filter(|n| n.ends_with("-reverse")) {
+            let forward = name.trim_end_matches("-reverse");
+            let input = "HELLO WORLD 42".to_owned();
+            let transformed = get(forward, None)
+                .unwrap()
+                .transform(input.clone())
+                .unwrap();
+            let reversed = get(name, None).unwrap().transform(transformed).unwrap();
+            assert!(
+                reversed.eq_ignore_ascii_case(&input),
+                "{}: {}",
+                name,
+                reversed
+            );
+        }
+    }
+}